@@ -0,0 +1,33 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rustix::time::{clock_gettime, ClockId};
+
+/// Current wall-clock time in Unix milliseconds. Deadlines are stored this
+/// way so they keep their meaning across a suspend or a restart.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// The same moment read from three clocks. Comparing two readings tells a
+/// suspend (the monotonic clock stops, the time since boot doesn't) from
+/// the wall clock being set (neither of the others follows it).
+#[derive(Debug, Clone, Copy)]
+pub struct Now {
+    pub wall_ms: u64,
+    pub awake: Instant,
+    pub since_boot: Duration,
+}
+
+pub fn now() -> Now {
+    let boot = clock_gettime(ClockId::Boottime);
+    Now {
+        wall_ms: now_ms(),
+        awake: Instant::now(),
+        since_boot: Duration::new(
+            u64::try_from(boot.tv_sec).unwrap_or(0),
+            u32::try_from(boot.tv_nsec).unwrap_or(0),
+        ),
+    }
+}
@@ -1,7 +1,17 @@
-use crate::config::RainConfig;
-use crate::pomodoro::{PomodoroPhase, PomodoroState};
-use crate::presets::Preset;
-use crate::state::AppState;
+use crate::clock;
+use crate::config::{ConfigIssue, OverlayLayer, RainConfig, RainRegion};
+use crate::playlist::{Playlist, PlaylistStatus};
+use crate::pomodoro::{PomodoroEvent, PomodoroState};
+use crate::pomodoro_history;
+use crate::pomodoro_stats::{self, FocusStats};
+use crate::preset_share::{self, ImportedPreset};
+use crate::presets::{self, PresetConfig, PresetEntry};
+use crate::profiles::ProfileList;
+use crate::rain::stats::OverlayStats;
+use crate::schema::{FieldSpec, ValidationError, ValidationMode};
+use crate::state::{AppState, ConfigUpdate, ConfigUpdateError, OverlayHealth};
+use serde_json::Value;
+use std::path::Path;
 use tauri::State;
 
 // ============================================================================
@@ -14,8 +24,38 @@ pub fn get_config(state: State<AppState>) -> RainConfig {
 }
 
 #[tauri::command]
-pub fn set_config(state: State<AppState>, config: RainConfig) -> Result<RainConfig, String> {
-    state.set_config(config)
+pub fn get_config_issue(state: State<AppState>) -> Option<ConfigIssue> {
+    state.config_issue.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_config_schema() -> Vec<FieldSpec> {
+    crate::schema::FIELDS.to_vec()
+}
+
+#[tauri::command]
+pub fn validate_config(config: Value) -> Result<(), ValidationError> {
+    RainConfig::from_payload(config)?.validate()
+}
+
+/// Replace the config; any out-of-range field rejects the whole update
+#[tauri::command]
+pub fn set_config(state: State<AppState>, config: Value) -> Result<RainConfig, ConfigUpdateError> {
+    let config = RainConfig::from_payload(config).map_err(ConfigUpdateError::Invalid)?;
+    state
+        .set_config(config, ValidationMode::Strict)
+        .map(|update| update.config)
+}
+
+/// Replace the config, clamping out-of-range fields and reporting them.
+/// Missing, unknown and mistyped fields can't be clamped and are rejected.
+#[tauri::command]
+pub fn set_config_lenient(
+    state: State<AppState>,
+    config: Value,
+) -> Result<ConfigUpdate, ConfigUpdateError> {
+    let config = RainConfig::from_payload(config).map_err(ConfigUpdateError::Invalid)?;
+    state.set_config(config, ValidationMode::Lenient)
 }
 
 #[tauri::command]
@@ -85,35 +125,253 @@ pub fn set_splash_intensity(
     state.update_config(|c| c.splash_intensity = intensity)
 }
 
+#[tauri::command]
+pub fn set_max_fps(state: State<AppState>, max_fps: u32) -> Result<RainConfig, String> {
+    state.update_config(|c| c.max_fps = max_fps)
+}
+
+#[tauri::command]
+pub fn set_close_to_background(
+    state: State<AppState>,
+    close_to_background: bool,
+) -> Result<RainConfig, String> {
+    state.update_config(|c| c.close_to_background = close_to_background)
+}
+
+#[tauri::command]
+pub fn set_layer(state: State<AppState>, layer: OverlayLayer) -> Result<RainConfig, String> {
+    state.update_config(|c| c.layer = layer)
+}
+
+#[tauri::command]
+pub fn set_region(state: State<AppState>, region: RainRegion) -> Result<RainConfig, String> {
+    state.update_config(|c| c.region = region)
+}
+
 // ============================================================================
 // Preset Commands
 // ============================================================================
 
 #[tauri::command]
-pub fn get_presets() -> Vec<Preset> {
-    crate::presets::get_builtin_presets()
+pub fn get_presets() -> Result<Vec<PresetEntry>, String> {
+    presets::list().map_err(|e| e.to_string())
+}
+
+/// Switch to a preset. The overlay animates to it over `transition_ms`, or
+/// over the configured transition duration when it's not given. Picking a
+/// preset by hand stops the playlist.
+#[tauri::command]
+pub fn apply_preset(
+    state: State<AppState>,
+    preset_name: String,
+    transition_ms: Option<u32>,
+) -> Result<RainConfig, String> {
+    let config = state.apply_preset(&preset_name, transition_ms)?;
+    if state.playlist_status().running {
+        state.stop_playlist()?;
+    }
+    Ok(config)
 }
 
+/// Save the current look as a new user preset
 #[tauri::command]
-pub fn apply_preset(state: State<AppState>, preset_name: String) -> Result<RainConfig, String> {
-    let presets = crate::presets::get_builtin_presets();
-    let preset = presets
+pub fn save_preset(
+    state: State<AppState>,
+    name: String,
+    description: String,
+) -> Result<Vec<PresetEntry>, String> {
+    let config = PresetConfig::from(&state.get_config());
+    let name = presets::create(&name, &description, config).map_err(|e| e.to_string())?;
+    state.update_config(|c| c.preset = Some(name))?;
+    get_presets()
+}
+
+/// Overwrite a user preset with the current look
+#[tauri::command]
+pub fn update_preset(
+    state: State<AppState>,
+    name: String,
+    description: Option<String>,
+) -> Result<Vec<PresetEntry>, String> {
+    let config = PresetConfig::from(&state.get_config());
+    presets::update(&name, description.as_deref(), config).map_err(|e| e.to_string())?;
+    get_presets()
+}
+
+#[tauri::command]
+pub fn clone_preset(source: String, name: String) -> Result<Vec<PresetEntry>, String> {
+    presets::clone(&source, &name).map_err(|e| e.to_string())?;
+    get_presets()
+}
+
+/// Rename a user preset; playlist entries follow it
+#[tauri::command]
+pub fn rename_preset(
+    state: State<AppState>,
+    old_name: String,
+    new_name: String,
+) -> Result<Vec<PresetEntry>, String> {
+    let new_name = state.rename_preset(&old_name, &new_name)?;
+    replace_active_preset(&state, &old_name, Some(new_name))?;
+    get_presets()
+}
+
+/// Delete a user preset; refused while the playlist plays it
+#[tauri::command]
+pub fn delete_preset(state: State<AppState>, name: String) -> Result<Vec<PresetEntry>, String> {
+    state.delete_preset(&name)?;
+    replace_active_preset(&state, &name, None)?;
+    get_presets()
+}
+
+#[tauri::command]
+pub fn set_preset_favorite(name: String, favorite: bool) -> Result<Vec<PresetEntry>, String> {
+    presets::set_favorite(&name, favorite).map_err(|e| e.to_string())?;
+    get_presets()
+}
+
+/// Encode a preset as a share string for pasting into chat
+#[tauri::command]
+pub fn export_preset(name: String) -> Result<String, String> {
+    let preset = presets::find(&name).map_err(|e| e.to_string())?;
+    Ok(preset_share::to_share_string(&preset))
+}
+
+#[tauri::command]
+pub fn import_preset(share: String) -> Result<Vec<ImportedPreset>, String> {
+    let preset = preset_share::from_share_string(&share).map_err(|e| e.to_string())?;
+    preset_share::import(vec![preset]).map_err(|e| e.to_string())
+}
+
+/// Write one or more presets to a `.raindesk-preset.toml` file; an existing
+/// file is only replaced when `overwrite` is set
+#[tauri::command]
+pub fn export_presets_file(
+    names: Vec<String>,
+    path: String,
+    overwrite: bool,
+) -> Result<(), String> {
+    let presets = names
         .iter()
-        .find(|p| p.name == preset_name)
-        .ok_or_else(|| format!("Preset '{}' not found", preset_name))?;
+        .map(|name| presets::find(name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    preset_share::write_file(presets, Path::new(&path), overwrite).map_err(|e| e.to_string())
+}
 
-    state.update_config(|c| {
-        c.intensity = preset.config.intensity;
-        c.speed = preset.config.speed;
-        c.angle = preset.config.angle;
-        c.drop_length = preset.config.drop_length;
-        c.drop_width = preset.config.drop_width;
-        c.color = preset.config.color.clone();
-        c.opacity = preset.config.opacity;
-        c.splash_enabled = preset.config.splash_enabled;
-        c.splash_intensity = preset.config.splash_intensity;
-        c.preset = Some(preset_name.clone());
-    })
+#[tauri::command]
+pub fn import_presets_file(path: String) -> Result<Vec<ImportedPreset>, String> {
+    let presets = preset_share::read_file(Path::new(&path)).map_err(|e| e.to_string())?;
+    preset_share::import(presets).map_err(|e| e.to_string())
+}
+
+/// Keep `config.preset` pointing at a renamed preset, or clear it once the
+/// preset is gone
+fn replace_active_preset(
+    state: &AppState,
+    old_name: &str,
+    new_name: Option<String>,
+) -> Result<(), String> {
+    let is_active = |c: &RainConfig| {
+        c.preset
+            .as_deref()
+            .is_some_and(|p| presets::key(p) == presets::key(old_name))
+    };
+    if is_active(&state.get_config()) {
+        state.update_config(|c| c.preset = new_name)?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Playlist Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_playlist(state: State<AppState>) -> PlaylistStatus {
+    state.playlist_status()
+}
+
+/// Replace the playlist; a running playlist restarts from its first preset
+#[tauri::command]
+pub fn set_playlist(state: State<AppState>, playlist: Playlist) -> Result<PlaylistStatus, String> {
+    state.set_playlist(playlist)
+}
+
+#[tauri::command]
+pub fn start_playlist(state: State<AppState>) -> Result<PlaylistStatus, String> {
+    state.start_playlist()
+}
+
+#[tauri::command]
+pub fn stop_playlist(state: State<AppState>) -> Result<PlaylistStatus, String> {
+    state.stop_playlist()
+}
+
+#[tauri::command]
+pub fn next_playlist_preset(state: State<AppState>) -> Result<PlaylistStatus, String> {
+    state.skip_playlist(true)
+}
+
+#[tauri::command]
+pub fn previous_playlist_preset(state: State<AppState>) -> Result<PlaylistStatus, String> {
+    state.skip_playlist(false)
+}
+
+// ============================================================================
+// Profile Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn list_profiles(state: State<AppState>) -> Result<ProfileList, String> {
+    state.profiles()
+}
+
+#[tauri::command]
+pub fn create_profile(state: State<AppState>, name: String) -> Result<ProfileList, String> {
+    state.create_profile(&name, None)
+}
+
+#[tauri::command]
+pub fn clone_profile(
+    state: State<AppState>,
+    source: String,
+    name: String,
+) -> Result<ProfileList, String> {
+    state.create_profile(&name, Some(&source))
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    state: State<AppState>,
+    old_name: String,
+    new_name: String,
+) -> Result<ProfileList, String> {
+    state.rename_profile(&old_name, &new_name)
+}
+
+#[tauri::command]
+pub fn delete_profile(state: State<AppState>, name: String) -> Result<ProfileList, String> {
+    state.delete_profile(&name)
+}
+
+#[tauri::command]
+pub fn switch_profile(state: State<AppState>, name: String) -> Result<RainConfig, String> {
+    state.switch_profile(&name)
+}
+
+// ============================================================================
+// Overlay Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_overlay_health(state: State<AppState>) -> OverlayHealth {
+    state.overlay_health.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_overlay_stats(state: State<AppState>) -> OverlayStats {
+    state.overlay_stats.lock().unwrap().clone()
 }
 
 // ============================================================================
@@ -127,51 +385,22 @@ pub fn get_pomodoro(state: State<AppState>) -> PomodoroState {
 
 #[tauri::command]
 pub fn start_pomodoro(state: State<AppState>) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    pomodoro.start();
-    pomodoro.clone()
+    state.change_pomodoro(PomodoroState::start)
 }
 
 #[tauri::command]
 pub fn pause_pomodoro(state: State<AppState>) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    pomodoro.pause();
-    pomodoro.clone()
+    state.change_pomodoro(PomodoroState::pause)
 }
 
 #[tauri::command]
 pub fn reset_pomodoro(state: State<AppState>) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    pomodoro.reset();
-    pomodoro.clone()
+    state.change_pomodoro(PomodoroState::reset)
 }
 
 #[tauri::command]
 pub fn skip_pomodoro_phase(state: State<AppState>) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    pomodoro.skip_phase();
-    pomodoro.clone()
-}
-
-#[tauri::command]
-pub fn tick_pomodoro(state: State<AppState>) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    if let Some(new_phase) = pomodoro.tick() {
-        let (summary, body) = match new_phase {
-            PomodoroPhase::Work => ("Back to Work!", "Focus time has started."),
-            PomodoroPhase::ShortBreak => ("Short Break", "Take a quick breather."),
-            PomodoroPhase::LongBreak => ("Long Break", "Great job! Take a longer rest."),
-        };
-        std::thread::spawn(move || {
-            let _ = notify_rust::Notification::new()
-                .appname("RainDesk")
-                .summary(summary)
-                .body(body)
-                .timeout(5000)
-                .show();
-        });
-    }
-    pomodoro.clone()
+    state.change_pomodoro(PomodoroState::skip_phase)
 }
 
 #[tauri::command]
@@ -181,15 +410,42 @@ pub fn set_pomodoro_durations(
     short_break_mins: u32,
     long_break_mins: u32,
     sessions_until_long_break: u32,
-) -> PomodoroState {
-    let mut pomodoro = state.pomodoro.lock().unwrap();
-    pomodoro.work_duration_secs = work_mins * 60;
-    pomodoro.short_break_duration_secs = short_break_mins * 60;
-    pomodoro.long_break_duration_secs = long_break_mins * 60;
-    pomodoro.sessions_until_long_break = sessions_until_long_break;
-    // Reset timer if durations changed
-    if pomodoro.phase == PomodoroPhase::Work {
-        pomodoro.remaining_secs = pomodoro.work_duration_secs;
-    }
-    pomodoro.clone()
+) -> Result<PomodoroState, String> {
+    // Durations are part of the profile; saving them updates the timer too
+    state.update_config(|c| {
+        c.pomodoro.work_duration_secs = work_mins.saturating_mul(60);
+        c.pomodoro.short_break_duration_secs = short_break_mins.saturating_mul(60);
+        c.pomodoro.long_break_duration_secs = long_break_mins.saturating_mul(60);
+        c.pomodoro.sessions_until_long_break = sessions_until_long_break;
+    })?;
+    Ok(state.pomodoro.lock().unwrap().clone())
+}
+
+/// Timer events from `from_ms` up to `to_ms` (Unix milliseconds), oldest first
+#[tauri::command]
+pub fn get_pomodoro_history(
+    from_ms: Option<u64>,
+    to_ms: Option<u64>,
+) -> Result<Vec<PomodoroEvent>, String> {
+    pomodoro_history::query(from_ms, to_ms).map_err(|e| e.to_string())
+}
+
+/// Focus statistics from `from_ms` up to `to_ms` (Unix milliseconds; up to
+/// now when not given), with days in a zone `utc_offset_mins` ahead of UTC
+#[tauri::command]
+pub fn get_pomodoro_stats(
+    state: State<AppState>,
+    from_ms: Option<u64>,
+    to_ms: Option<u64>,
+    utc_offset_mins: i32,
+) -> Result<FocusStats, String> {
+    let daily_goal_mins = state.get_config().pomodoro.daily_goal_mins;
+    pomodoro_stats::focus_stats(
+        from_ms,
+        to_ms,
+        utc_offset_mins,
+        daily_goal_mins,
+        clock::now_ms(),
+    )
+    .map_err(|e| e.to_string())
 }
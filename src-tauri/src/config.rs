@@ -1,8 +1,29 @@
+use crate::pomodoro::PomodoroSettings;
+use crate::schema::{self, FieldError, RangeCheck, ValidationError, ValidationMode};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
+/// Current config file schema version. Bump it and append a step to
+/// `MIGRATIONS` when a field is renamed or changes meaning; a new field only
+/// needs a default.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrade steps: `MIGRATIONS[i]` turns a version `i + 1` table into `i + 2`.
+/// Every field added so far falls back to its default, so there are none yet.
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[];
+
+/// Previous good versions kept next to a config file as `<file>.1` (newest) to `<file>.N`
+const KEEP_BACKUPS: usize = 3;
+
+/// A new backup is taken at most this often, so the flurry of saves from
+/// dragging a slider doesn't push out every older copy
+const BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Failed to get config directory")]
@@ -13,10 +34,23 @@ pub enum ConfigError {
     TomlSerialize(#[from] toml::ser::Error),
     #[error("TOML deserialization error: {0}")]
     TomlDeserialize(#[from] toml::de::Error),
+    #[error("Invalid config version: {0}")]
+    InvalidVersion(String),
+    #[error("Config version {0} is newer than this release supports (version {CONFIG_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// Problem found while loading the config file, reported to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigIssue {
+    pub message: String,
+    /// Where the unusable file was moved, if it could be
+    pub backup_path: Option<String>,
 }
 
 /// RGBA color representation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct RainColor {
     pub r: u8,
     pub g: u8,
@@ -35,9 +69,128 @@ impl Default for RainColor {
     }
 }
 
-/// Central configuration shared between Rust backend and Vue frontend
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Layer-shell layer the overlay surfaces are placed on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlayLayer {
+    /// Behind all windows, like an animated wallpaper
+    Background,
+    /// Above the wallpaper, below regular windows
+    Bottom,
+    /// Above regular windows, below fullscreen ones
+    Top,
+    /// Above everything, including fullscreen windows
+    #[default]
+    Overlay,
+}
+
+/// Screen region covered by the overlay on each output, expressed as
+/// layer-shell anchors, margins and an optional fixed size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RainRegion {
+    pub anchor_top: bool,
+    pub anchor_bottom: bool,
+    pub anchor_left: bool,
+    pub anchor_right: bool,
+
+    /// Margins from the anchored edges, in logical pixels
+    pub margin_top: i32,
+    pub margin_right: i32,
+    pub margin_bottom: i32,
+    pub margin_left: i32,
+
+    /// Fixed width in logical pixels; `None` stretches between left and right
+    pub width: Option<u32>,
+
+    /// Fixed height in logical pixels; `None` stretches between top and bottom
+    pub height: Option<u32>,
+}
+
+impl Default for RainRegion {
+    fn default() -> Self {
+        Self {
+            anchor_top: true,
+            anchor_bottom: true,
+            anchor_left: true,
+            anchor_right: true,
+            margin_top: 0,
+            margin_right: 0,
+            margin_bottom: 0,
+            margin_left: 0,
+            width: None,
+            height: None,
+        }
+    }
+}
+
+impl RainRegion {
+    /// Clamp margins and sizes. A size of 0 means unset. A dimension
+    /// without a fixed size must be anchored to both opposite edges, as
+    /// layer-shell requires.
+    pub fn clamp(&mut self, check: &mut RangeCheck) {
+        check.number(&mut self.margin_top, &schema::MARGIN_TOP);
+        check.number(&mut self.margin_right, &schema::MARGIN_RIGHT);
+        check.number(&mut self.margin_bottom, &schema::MARGIN_BOTTOM);
+        check.number(&mut self.margin_left, &schema::MARGIN_LEFT);
+        self.width = self.width.filter(|w| *w > 0);
+        self.height = self.height.filter(|h| *h > 0);
+        check.optional(&mut self.width, &schema::WIDTH);
+        check.optional(&mut self.height, &schema::HEIGHT);
+        if self.width.is_none() {
+            let reason = "when region.width is not set";
+            check.require("region.anchor_left", &mut self.anchor_left, reason);
+            check.require("region.anchor_right", &mut self.anchor_right, reason);
+        }
+        if self.height.is_none() {
+            let reason = "when region.height is not set";
+            check.require("region.anchor_top", &mut self.anchor_top, reason);
+            check.require("region.anchor_bottom", &mut self.anchor_bottom, reason);
+        }
+    }
+}
+
+/// Curve that shapes how a transition moves from the old look to the new one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    /// Constant rate of change
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down
+    EaseOut,
+    /// Slow at both ends
+    #[default]
+    EaseInOut,
+}
+
+/// How the overlay animates between the old and new look when the config
+/// changes; 0 ms applies changes instantly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitionSettings {
+    pub duration_ms: u32,
+    pub easing: Easing,
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        Self {
+            duration_ms: 800,
+            easing: Easing::default(),
+        }
+    }
+}
+
+/// Central configuration shared between Rust backend and Vue frontend.
+/// Missing fields take their value from `RainConfig::default()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RainConfig {
+    /// Schema version; always `CONFIG_VERSION` once loaded
+    pub version: u32,
+
     /// Whether rain effect is enabled
     pub enabled: bool,
 
@@ -70,11 +223,30 @@ pub struct RainConfig {
 
     /// Current preset name (if any)
     pub preset: Option<String>,
+
+    /// Layer-shell layer for the overlay
+    pub layer: OverlayLayer,
+
+    /// Region of each output the rain is confined to
+    pub region: RainRegion,
+
+    /// Frame rate cap (0 = follow the display refresh rate)
+    pub max_fps: u32,
+
+    /// Closing the window hides it and keeps the rain running
+    pub close_to_background: bool,
+
+    /// Animation between looks when the config changes
+    pub transition: TransitionSettings,
+
+    /// Pomodoro durations
+    pub pomodoro: PomodoroSettings,
 }
 
 impl Default for RainConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             enabled: true,
             intensity: 0.5,
             speed: 1.0,
@@ -86,50 +258,454 @@ impl Default for RainConfig {
             splash_enabled: true,
             splash_intensity: 0.5,
             preset: None,
+            layer: OverlayLayer::default(),
+            region: RainRegion::default(),
+            max_fps: 0,
+            close_to_background: false,
+            transition: TransitionSettings::default(),
+            pomodoro: PomodoroSettings::default(),
         }
     }
 }
 
 impl RainConfig {
-    /// Get the config file path
-    pub fn config_path() -> Result<PathBuf, ConfigError> {
+    /// Get the config directory
+    pub fn config_dir() -> Result<PathBuf, ConfigError> {
         let proj_dirs = directories::ProjectDirs::from("com", "danielhe4rt", "raindesk")
             .ok_or(ConfigError::NoConfigDir)?;
-        let config_dir = proj_dirs.config_dir();
-        Ok(config_dir.join("config.toml"))
+        Ok(proj_dirs.config_dir().to_path_buf())
     }
 
-    /// Load config from disk, or return default if not found
-    pub fn load() -> Result<Self, ConfigError> {
-        let path = Self::config_path()?;
-        if path.exists() {
-            let contents = fs::read_to_string(&path)?;
-            let config: RainConfig = toml::from_str(&contents)?;
-            Ok(config)
-        } else {
-            Ok(Self::default())
+    /// Get the config file path of the default profile
+    pub fn config_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::config_dir()?.join("config.toml"))
+    }
+
+    /// Load config from `path`, or return default if not found. Files
+    /// written by older releases are migrated and upgraded in place.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        let (mut config, version) = Self::parse(&contents)?;
+        let clamped = config.clamp();
+        if !clamped.is_empty() {
+            eprintln!(
+                "[raindesk] Clamped out-of-range settings in {}: {}",
+                path.display(),
+                schema::describe(&clamped)
+            );
+        }
+        if version < CONFIG_VERSION {
+            // The upgraded config is already in memory; a failed write is retried on the next save
+            match config.upgrade_file(path, version) {
+                Ok(()) => eprintln!(
+                    "[raindesk] Upgraded config from version {} to {}",
+                    version, CONFIG_VERSION
+                ),
+                Err(e) => eprintln!("[raindesk] Failed to upgrade config file: {}", e),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse and migrate the contents of a config file, without clamping.
+    /// Returns the config and the version the file was written as.
+    fn parse(contents: &str) -> Result<(Self, u32), ConfigError> {
+        let mut table: toml::Table = contents.parse()?;
+
+        // Files from before versioning have no version field
+        let version = match table.get("version") {
+            None => 1,
+            Some(toml::Value::Integer(v)) => u32::try_from(*v)
+                .ok()
+                .filter(|v| *v >= 1)
+                .ok_or_else(|| ConfigError::InvalidVersion(v.to_string()))?,
+            Some(other) => return Err(ConfigError::InvalidVersion(other.to_string())),
+        };
+        if version > CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion(version));
+        }
+        for migrate in &MIGRATIONS[(version - 1) as usize..] {
+            migrate(&mut table);
         }
+        table.insert("version".to_string(), CONFIG_VERSION.into());
+        Ok((table.try_into()?, version))
     }
 
-    /// Save config to disk
-    pub fn save(&self) -> Result<(), ConfigError> {
-        let path = Self::config_path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Load config, falling back to defaults when the file can't be used.
+    /// The unusable file is moved aside so the next save doesn't overwrite it,
+    /// and the returned issue tells the UI what happened.
+    pub fn load_or_recover(path: &Path) -> (Self, Option<ConfigIssue>) {
+        match Self::load(path) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                eprintln!("[raindesk] Failed to load config, using defaults: {}", e);
+                let backup_path = match back_up_invalid(path) {
+                    Ok(path) => Some(path.display().to_string()),
+                    Err(backup_err) => {
+                        eprintln!("[raindesk] Failed to back up config: {}", backup_err);
+                        None
+                    }
+                };
+                let issue = ConfigIssue {
+                    message: format!("Settings could not be loaded and were reset: {}", e),
+                    backup_path,
+                };
+                (Self::default(), Some(issue))
+            }
         }
+    }
+
+    /// Keep a copy of the old file as `<name>.v<N>.toml.bak`, then save the
+    /// upgraded config over it
+    fn upgrade_file(&self, path: &Path, from_version: u32) -> Result<(), ConfigError> {
+        fs::copy(
+            path,
+            path.with_file_name(format!("{}.v{}.toml.bak", file_stem(path), from_version)),
+        )?;
+        self.save(path)
+    }
+
+    /// Save config to `path` atomically: the new contents go to a temp file
+    /// that is fsynced and renamed over the config file, so a crash leaves
+    /// either the old file or the new one. The old file becomes the newest
+    /// numbered backup if it's valid, differs from that backup, and the
+    /// last backup was taken over `BACKUP_INTERVAL` ago.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
         let contents = toml::to_string_pretty(self)?;
-        fs::write(&path, contents)?;
+        if needs_backup(path) && is_valid_config(path) {
+            rotate_backups(path)?;
+        }
+        write_atomically(path, &contents)?;
         Ok(())
     }
 
-    /// Clamp all values to valid ranges
-    pub fn clamp(&mut self) {
-        self.intensity = self.intensity.clamp(0.0, 1.0);
-        self.speed = self.speed.clamp(0.5, 5.0);
-        self.angle = self.angle.clamp(-60.0, 60.0);
-        self.drop_length = self.drop_length.clamp(5.0, 100.0);
-        self.drop_width = self.drop_width.clamp(1.0, 10.0);
-        self.opacity = self.opacity.clamp(0.0, 1.0);
-        self.splash_intensity = self.splash_intensity.clamp(0.0, 1.0);
+    /// Clamp all values to valid ranges, returning the fields that were changed
+    pub fn clamp(&mut self) -> Vec<FieldError> {
+        let mut check = RangeCheck::default();
+        check.number(&mut self.intensity, &schema::INTENSITY);
+        check.number(&mut self.speed, &schema::SPEED);
+        check.number(&mut self.angle, &schema::ANGLE);
+        check.number(&mut self.drop_length, &schema::DROP_LENGTH);
+        check.number(&mut self.drop_width, &schema::DROP_WIDTH);
+        check.number(&mut self.opacity, &schema::OPACITY);
+        check.number(&mut self.splash_intensity, &schema::SPLASH_INTENSITY);
+        check.number(&mut self.max_fps, &schema::MAX_FPS);
+        check.number(
+            &mut self.transition.duration_ms,
+            &schema::TRANSITION_DURATION,
+        );
+        self.region.clamp(&mut check);
+        self.pomodoro.clamp(&mut check);
+        check.into_errors()
+    }
+
+    /// Check every field against its allowed range without changing anything
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let errors = self.clone().clamp();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError::new(errors))
+        }
+    }
+
+    /// Read a config sent by the UI. Unlike a config file, it must have
+    /// every field and no others; anything that doesn't fit is reported
+    /// per field.
+    pub fn from_payload(payload: Value) -> Result<Self, ValidationError> {
+        let expected =
+            serde_json::to_value(Self::default()).expect("the default config converts to JSON");
+        let errors = schema::shape_errors(&payload, &expected);
+        if !errors.is_empty() {
+            return Err(ValidationError::new(errors));
+        }
+        serde_json::from_value(payload.clone()).map_err(|e| {
+            // Right shape but a value serde won't take, e.g. an unknown
+            // easing or a color channel over 255
+            let (field, value) = schema::rejected_field(&payload, &expected, |candidate| {
+                serde_json::from_value::<Self>(candidate).is_ok()
+            })
+            .unwrap_or_default();
+            ValidationError::new(vec![FieldError {
+                field,
+                message: e.to_string(),
+                min: None,
+                max: None,
+                value,
+                clamped_to: None,
+            }])
+        })
+    }
+
+    /// Reject (strict) or clamp (lenient) out-of-range fields. Returns the
+    /// config to use and, in lenient mode, the fields that were clamped.
+    pub fn checked(
+        mut self,
+        mode: ValidationMode,
+    ) -> Result<(Self, Vec<FieldError>), ValidationError> {
+        let errors = self.clamp();
+        if mode == ValidationMode::Strict && !errors.is_empty() {
+            return Err(ValidationError::new(errors));
+        }
+        Ok((self, errors))
+    }
+}
+
+/// Move an unusable config file aside as `<name>.invalid-<unix millis>.toml`
+fn back_up_invalid(path: &Path) -> Result<PathBuf, ConfigError> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let backup = path.with_file_name(format!("{}.invalid-{}.toml", file_stem(path), stamp));
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+/// Write `contents` to `path` through a temp file that is fsynced and
+/// renamed over it, so readers never see a partial file and a crash leaves
+/// either the old contents or the new ones
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let tmp_path = with_suffix(path, ".tmp");
+    let mut tmp = fs::File::create(&tmp_path)?;
+    tmp.write_all(contents.as_bytes())?;
+    tmp.sync_all()?;
+    drop(tmp);
+    fs::rename(&tmp_path, path)?;
+    // Make the rename itself durable
+    fs::File::open(dir)?.sync_all()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| "config".into(), |s| s.to_string_lossy().into_owned())
+}
+
+/// Numbered backups kept for a config file, newest first
+pub fn backup_paths(path: &Path) -> Vec<PathBuf> {
+    (1..=KEEP_BACKUPS)
+        .map(|n| with_suffix(path, &format!(".{}", n)))
+        .collect()
+}
+
+/// Whether `path` holds a config file that loads as it is, with a
+/// supported version and every setting in range
+fn is_valid_config(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| RainConfig::parse(&contents).ok())
+        .is_some_and(|(config, _)| config.validate().is_ok())
+}
+
+/// Whether the file at `path` should become a new backup: it differs from
+/// the newest one, and that one was taken long enough ago
+fn needs_backup(path: &Path) -> bool {
+    let newest = &backup_paths(path)[0];
+    let Ok(backed_up) = fs::metadata(newest).and_then(|meta| meta.modified()) else {
+        return true;
+    };
+    let due = backed_up
+        .elapsed()
+        .map_or(true, |age| age >= BACKUP_INTERVAL);
+    due && fs::read(path).ok() != fs::read(newest).ok()
+}
+
+/// Shift `<file>.1..N-1` up by one, dropping the oldest, and keep the
+/// current file as `<file>.1`
+fn rotate_backups(path: &Path) -> io::Result<()> {
+    let backups = backup_paths(path);
+    for pair in backups.windows(2).rev() {
+        if pair[0].exists() {
+            fs::rename(&pair[0], &pair[1])?;
+        }
+    }
+    // A copy rather than a link, so its modification time says when the
+    // backup was taken
+    fs::copy(path, &backups[0]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn payload() -> Value {
+        serde_json::to_value(RainConfig::default()).unwrap()
+    }
+
+    fn fields(error: ValidationError) -> Vec<(String, String)> {
+        error
+            .errors
+            .into_iter()
+            .map(|error| (error.field, error.message))
+            .collect()
+    }
+
+    /// Empty scratch directory for a test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raindesk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn with_intensity(intensity: f32) -> RainConfig {
+        RainConfig {
+            intensity,
+            ..RainConfig::default()
+        }
+    }
+
+    fn saved_intensity(path: &Path) -> Option<f32> {
+        let contents = fs::read_to_string(path).ok()?;
+        Some(RainConfig::parse(&contents).ok()?.0.intensity)
+    }
+
+    #[test]
+    fn repeated_saves_keep_older_backups() {
+        let dir = scratch_dir("backups");
+        let path = dir.join("config.toml");
+        with_intensity(0.1).save(&path).unwrap();
+        with_intensity(0.2).save(&path).unwrap();
+        // An hour later, a slider is dragged
+        let backups = backup_paths(&path);
+        let an_hour_ago = SystemTime::now() - BACKUP_INTERVAL;
+        fs::File::options()
+            .write(true)
+            .open(&backups[0])
+            .unwrap()
+            .set_modified(an_hour_ago)
+            .unwrap();
+        for intensity in [0.3, 0.4, 0.5, 0.6, 0.7] {
+            with_intensity(intensity).save(&path).unwrap();
+        }
+
+        assert_eq!(saved_intensity(&path), Some(0.7));
+        assert_eq!(saved_intensity(&backups[0]), Some(0.2));
+        assert_eq!(saved_intensity(&backups[1]), Some(0.1));
+        assert_eq!(saved_intensity(&backups[2]), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn out_of_range_files_are_not_backed_up() {
+        let dir = scratch_dir("invalid-backup");
+        let path = dir.join("config.toml");
+        let contents = format!("version = {}\nintensity = 7.0\n", CONFIG_VERSION);
+        fs::write(&path, contents).unwrap();
+        RainConfig::default().save(&path).unwrap();
+        assert!(!backup_paths(&path)[0].exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strict_mode_rejects_and_lenient_mode_clamps() {
+        let config = RainConfig {
+            angle: -75.0,
+            ..with_intensity(1.5)
+        };
+        let error = config.clone().checked(ValidationMode::Strict).unwrap_err();
+        assert_eq!(
+            fields(error),
+            [
+                (
+                    "intensity".to_string(),
+                    "must be between 0 and 1, got 1.5".to_string()
+                ),
+                (
+                    "angle".to_string(),
+                    "must be between -60 and 60, got -75".to_string()
+                ),
+            ]
+        );
+
+        let (clamped, errors) = config.checked(ValidationMode::Lenient).unwrap();
+        assert_eq!((clamped.intensity, clamped.angle), (1.0, -60.0));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].clamped_to, Some(json!(-60.0)));
+
+        let (config, errors) = RainConfig::default()
+            .checked(ValidationMode::Strict)
+            .unwrap();
+        assert_eq!(config, RainConfig::default());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn complete_payload_is_accepted() {
+        let mut payload = payload();
+        payload["preset"] = json!("Storm");
+        payload["region"]["width"] = json!(800);
+        let config = RainConfig::from_payload(payload).unwrap();
+        assert_eq!(config.preset.as_deref(), Some("Storm"));
+        assert_eq!(config.region.width, Some(800));
+    }
+
+    #[test]
+    fn missing_and_unknown_fields_are_reported() {
+        let mut payload = payload();
+        payload.as_object_mut().unwrap().remove("intensity");
+        let pomodoro = payload["pomodoro"].as_object_mut().unwrap();
+        let work = pomodoro.remove("work_duration_secs").unwrap();
+        pomodoro.insert("work_duraton_secs".to_string(), work);
+
+        let error = RainConfig::from_payload(payload).unwrap_err();
+        assert_eq!(
+            fields(error),
+            [
+                ("intensity".to_string(), "is missing".to_string()),
+                (
+                    "pomodoro.work_duration_secs".to_string(),
+                    "is missing".to_string()
+                ),
+                (
+                    "pomodoro.work_duraton_secs".to_string(),
+                    "is not a setting".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_types_are_reported_per_field() {
+        let mut payload = payload();
+        payload["enabled"] = json!("yes");
+        payload["color"] = json!(7);
+        let error = RainConfig::from_payload(payload).unwrap_err();
+        assert_eq!(
+            fields(error),
+            [
+                ("color".to_string(), "must be an object".to_string()),
+                ("enabled".to_string(), "must be true or false".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_serde_rejects_are_pinned_to_their_field() {
+        let mut payload = payload();
+        payload["color"]["r"] = json!(300);
+        let error = RainConfig::from_payload(payload).unwrap_err();
+        assert_eq!(error.errors.len(), 1);
+        assert_eq!(error.errors[0].field, "color.r");
+        assert_eq!(error.errors[0].value, json!(300));
+
+        let mut payload = self::payload();
+        payload["transition"]["easing"] = json!("bounce");
+        let error = RainConfig::from_payload(payload).unwrap_err();
+        assert_eq!(error.errors[0].field, "transition.easing");
     }
 }
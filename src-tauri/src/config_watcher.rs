@@ -0,0 +1,116 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::thread;
+use std::time::Duration;
+
+use rustix::fs::inotify;
+use rustix::io::Errno;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::{ConfigIssue, RainConfig};
+use crate::profiles;
+use crate::state::AppState;
+
+/// Event emitted to the UI with the new config after it was reloaded from disk
+pub const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+/// Event emitted to the UI when an edited config file can't be applied
+pub const CONFIG_ERROR_EVENT: &str = "config-error";
+
+/// Lets an editor or script finish writing before the file is read
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Watch the active profile's config file on a dedicated thread and apply
+/// changes made outside the app (editors, dotfile managers, scripts)
+pub fn spawn(app: AppHandle) {
+    let spawned = thread::Builder::new()
+        .name("raindesk-config-watch".to_string())
+        .spawn(move || {
+            if let Err(e) = watch(&app) {
+                eprintln!("[raindesk] Config watcher stopped: {}", e);
+            }
+        });
+    if let Err(e) = spawned {
+        eprintln!("[raindesk] Config watcher not started: {}", e);
+    }
+}
+
+fn watch(app: &AppHandle) -> Result<(), String> {
+    let config_dir = RainConfig::config_dir().map_err(|e| e.to_string())?;
+    let profiles_dir = profiles::profiles_dir().map_err(|e| e.to_string())?;
+
+    // Watch the directories rather than the file: atomic saves rename a new
+    // file into place, which a watch on the old inode would never see
+    let inotify = inotify::init(inotify::CreateFlags::CLOEXEC)
+        .map_err(|e| format!("inotify_init: {}", e))?;
+    let mut watches = Vec::new();
+    for dir in [config_dir, profiles_dir] {
+        fs::create_dir_all(&dir).map_err(|e| format!("Create {}: {}", dir.display(), e))?;
+        let wd = inotify::add_watch(
+            &inotify,
+            &dir,
+            inotify::WatchFlags::CLOSE_WRITE | inotify::WatchFlags::MOVED_TO,
+        )
+        .map_err(|e| format!("inotify_add_watch: {}", e))?;
+        watches.push((wd, dir));
+    }
+
+    let state = app.state::<AppState>();
+    let mut buf = [MaybeUninit::uninit(); 4096];
+    let mut events = inotify::Reader::new(&inotify, &mut buf);
+    let mut changed = false;
+    loop {
+        match events.next() {
+            Ok(event) => {
+                let dir = watches.iter().find(|(wd, _)| *wd == event.wd());
+                if let (Some((_, dir)), Some(name)) = (dir, event.file_name()) {
+                    let path = dir.join(OsStr::from_bytes(name.to_bytes()));
+                    changed |= state.active_profile_path().is_ok_and(|active| active == path);
+                }
+            }
+            Err(Errno::INTR) => continue,
+            Err(e) => return Err(format!("inotify read: {}", e)),
+        }
+        // Reload once per batch of events
+        if changed && events.is_buffer_empty() {
+            changed = false;
+            thread::sleep(SETTLE);
+            reload(app);
+        }
+    }
+}
+
+/// Re-read the active profile's file and apply it; a file that doesn't
+/// load is reported and the running config is kept
+fn reload(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Ok(path) = state.active_profile_path() else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return;
+    };
+    if state.config_writer.is_own_write(&contents) {
+        return;
+    }
+    match RainConfig::load(&path) {
+        Ok(config) => {
+            *state.config_issue.lock().unwrap() = None;
+            if state.adopt_config(path, config.clone()) {
+                eprintln!("[raindesk] Reloaded config from disk");
+                let _ = app.emit(CONFIG_CHANGED_EVENT, config);
+            }
+        }
+        Err(e) => {
+            eprintln!("[raindesk] Ignoring invalid config on disk: {}", e);
+            let issue = ConfigIssue {
+                message: format!("{} was not applied: {}", path.display(), e),
+                backup_path: None,
+            };
+            *state.config_issue.lock().unwrap() = Some(issue.clone());
+            let _ = app.emit(CONFIG_ERROR_EVENT, issue);
+        }
+    }
+}
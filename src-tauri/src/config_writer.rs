@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::RainConfig;
+
+/// Quiet period after the last change before the config is written
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Longest a change waits while new ones keep arriving (e.g. a dragged slider)
+const MAX_DELAY: Duration = Duration::from_secs(3);
+
+/// How long `flush` waits for the pending write
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum WriterMsg {
+    Save(PathBuf, RainConfig),
+    /// The file at this path was changed outside the app and that version
+    /// wins: drop the snapshot pending for it
+    Discard(PathBuf),
+    /// Write any pending snapshot now, then acknowledge
+    Flush(mpsc::Sender<()>),
+}
+
+/// Persists config snapshots on a background thread so callers never block
+/// on disk I/O. A burst of changes is collapsed into one write of the
+/// latest snapshot.
+pub struct ConfigWriter {
+    tx: mpsc::Sender<WriterMsg>,
+    /// Contents of the last file written, to tell our saves from outside edits
+    last_written: Arc<Mutex<Option<String>>>,
+}
+
+impl ConfigWriter {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let last_written = Arc::new(Mutex::new(None));
+        let writer_last_written = last_written.clone();
+        thread::Builder::new()
+            .name("raindesk-config".to_string())
+            .spawn(move || run(rx, &writer_last_written))
+            .expect("failed to spawn config writer thread");
+        Self { tx, last_written }
+    }
+
+    /// Whether `contents` is exactly what this writer last saved
+    pub fn is_own_write(&self, contents: &str) -> bool {
+        self.last_written.lock().unwrap().as_deref() == Some(contents)
+    }
+
+    /// Queue a snapshot to be written to `path` once changes settle
+    pub fn save(&self, path: PathBuf, config: RainConfig) {
+        let _ = self.tx.send(WriterMsg::Save(path, config));
+    }
+
+    /// Drop the snapshot pending for `path`, if any, so a change made in the
+    /// app just before an outside edit doesn't overwrite that edit. Queued
+    /// behind earlier snapshots, so every one sent before this is dropped.
+    pub fn discard(&self, path: PathBuf) {
+        let _ = self.tx.send(WriterMsg::Discard(path));
+    }
+
+    /// Write the pending snapshot, if any, and wait (bounded) until it's on disk
+    pub fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        if self.tx.send(WriterMsg::Flush(ack_tx)).is_ok()
+            && ack_rx.recv_timeout(FLUSH_TIMEOUT).is_err()
+        {
+            eprintln!("[raindesk] Config write did not finish in time");
+        }
+    }
+}
+
+fn run(rx: mpsc::Receiver<WriterMsg>, last_written: &Mutex<Option<String>>) {
+    let mut pending: Option<Pending> = None;
+    loop {
+        let msg = match &pending {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(Pending { since, .. }) => {
+                let deadline = (Instant::now() + DEBOUNCE).min(*since + MAX_DELAY);
+                rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+        };
+        match msg {
+            Ok(WriterMsg::Save(path, config)) => {
+                // A snapshot for another file (profile switch) can't wait for this one
+                if pending.as_ref().is_some_and(|p| p.path != path) {
+                    write(pending.take(), last_written);
+                }
+                let since = pending.map_or_else(Instant::now, |p| p.since);
+                pending = Some(Pending { path, config, since });
+            }
+            Ok(WriterMsg::Discard(path)) => {
+                if pending.as_ref().is_some_and(|p| p.path == path) {
+                    pending = None;
+                }
+                // A write that raced the outside edit is no longer ours to
+                // skip: the watcher reloads it, so memory follows the file
+                *last_written.lock().unwrap() = None;
+            }
+            Ok(WriterMsg::Flush(ack)) => {
+                write(pending.take(), last_written);
+                let _ = ack.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => write(pending.take(), last_written),
+            Err(RecvTimeoutError::Disconnected) => {
+                write(pending.take(), last_written);
+                return;
+            }
+        }
+    }
+}
+
+/// Latest unsaved snapshot
+struct Pending {
+    path: PathBuf,
+    config: RainConfig,
+    /// When the oldest unsaved change came in
+    since: Instant,
+}
+
+fn write(pending: Option<Pending>, last_written: &Mutex<Option<String>>) {
+    let Some(Pending { path, config, .. }) = pending else {
+        return;
+    };
+    // Recorded first: the config watcher may read the file as soon as it's renamed
+    *last_written.lock().unwrap() = toml::to_string_pretty(&config).ok();
+    if let Err(e) = config.save(&path) {
+        eprintln!("[raindesk] Failed to save config: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn discard_drops_snapshots_queued_before_it() {
+        let dir = std::env::temp_dir().join(format!("raindesk-writer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let writer = ConfigWriter::spawn();
+
+        // A slider moved just before the file was edited outside the app
+        writer.save(path.clone(), RainConfig::default());
+        writer.discard(path.clone());
+        writer.flush();
+        assert!(!path.exists());
+
+        // Changes made after the edit are saved as usual
+        let config = RainConfig {
+            intensity: 0.25,
+            ..RainConfig::default()
+        };
+        writer.save(path.clone(), config.clone());
+        writer.flush();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(writer.is_own_write(&contents));
+        assert_eq!(toml::from_str::<RainConfig>(&contents).unwrap(), config);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,54 +1,61 @@
+mod clock;
 mod commands;
 mod config;
+mod config_watcher;
+mod config_writer;
+mod playlist;
+mod playlist_player;
 mod pomodoro;
+mod pomodoro_clock;
+mod pomodoro_history;
+mod pomodoro_stats;
+mod preset_share;
 mod presets;
+mod profiles;
 mod rain;
+mod schema;
 mod state;
+mod supervisor;
+mod tray;
 
 use state::AppState;
-use std::panic;
-use std::sync::mpsc;
+use tauri::{Manager, RunEvent, WindowEvent};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let app_state = AppState::new();
 
-    // Set up overlay communication channel
-    let (overlay_tx, overlay_rx) = mpsc::channel();
-    *app_state.overlay_tx.lock().unwrap() = Some(overlay_tx);
-
-    // Clone the Arc<Mutex<RainConfig>> for the overlay thread
-    let overlay_config = app_state.config.clone();
-
-    // Spawn overlay on a dedicated OS thread (it runs a blocking Wayland event loop)
-    std::thread::Builder::new()
-        .name("raindesk-overlay".to_string())
-        .spawn(move || {
-            match panic::catch_unwind(panic::AssertUnwindSafe(|| {
-                rain::overlay::run_overlay(overlay_config, overlay_rx);
-            })) {
-                Ok(()) => {}
-                Err(e) => {
-                    let msg = if let Some(s) = e.downcast_ref::<&str>() {
-                        s.to_string()
-                    } else if let Some(s) = e.downcast_ref::<String>() {
-                        s.clone()
-                    } else {
-                        "unknown panic".to_string()
-                    };
-                    eprintln!("[raindesk overlay] PANIC: {}", msg);
-                }
-            }
-        })
-        .expect("failed to spawn overlay thread");
-
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(app_state)
+        .setup(|app| {
+            // The overlay needs the managed state, so it starts once the app exists
+            supervisor::spawn(app.handle());
+            config_watcher::spawn(app.handle().clone());
+            playlist_player::spawn(app.handle().clone());
+            pomodoro_clock::spawn(app.handle().clone());
+            tray::create(app)?;
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                if window.state::<AppState>().get_config().close_to_background {
+                    // Keep the app and the rain alive; the tray icon brings the window back
+                    api.prevent_close();
+                    let _ = window.hide();
+                } else {
+                    supervisor::shutdown(window.app_handle());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Rain config commands
             commands::get_config,
+            commands::get_config_issue,
+            commands::get_config_schema,
+            commands::validate_config,
             commands::set_config,
+            commands::set_config_lenient,
             commands::set_enabled,
             commands::set_intensity,
             commands::set_speed,
@@ -59,18 +66,57 @@ pub fn run() {
             commands::set_opacity,
             commands::set_splash_enabled,
             commands::set_splash_intensity,
+            commands::set_max_fps,
+            commands::set_layer,
+            commands::set_region,
+            commands::set_close_to_background,
             // Preset commands
             commands::get_presets,
             commands::apply_preset,
+            commands::save_preset,
+            commands::update_preset,
+            commands::clone_preset,
+            commands::rename_preset,
+            commands::delete_preset,
+            commands::set_preset_favorite,
+            commands::export_preset,
+            commands::import_preset,
+            commands::export_presets_file,
+            commands::import_presets_file,
+            // Playlist commands
+            commands::get_playlist,
+            commands::set_playlist,
+            commands::start_playlist,
+            commands::stop_playlist,
+            commands::next_playlist_preset,
+            commands::previous_playlist_preset,
+            // Profile commands
+            commands::list_profiles,
+            commands::create_profile,
+            commands::clone_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::switch_profile,
+            // Overlay commands
+            commands::get_overlay_health,
+            commands::get_overlay_stats,
             // Pomodoro commands
             commands::get_pomodoro,
             commands::start_pomodoro,
             commands::pause_pomodoro,
             commands::reset_pomodoro,
             commands::skip_pomodoro_phase,
-            commands::tick_pomodoro,
             commands::set_pomodoro_durations,
+            commands::get_pomodoro_history,
+            commands::get_pomodoro_stats,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                supervisor::shutdown(app);
+                app.state::<AppState>().config_writer.flush();
+                app.state::<AppState>().save_pomodoro();
+            }
+        });
 }
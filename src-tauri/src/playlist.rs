@@ -0,0 +1,444 @@
+use crate::clock::now_ms;
+use crate::config::{self, ConfigError, RainConfig};
+use crate::presets::{self, PresetError};
+use crate::schema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Shortest time a preset can stay on screen
+const MIN_DWELL_SECS: u32 = 10;
+
+/// Longest time a preset can stay on screen (one day)
+const MAX_DWELL_SECS: u32 = 24 * 60 * 60;
+
+#[derive(Error, Debug)]
+pub enum PlaylistError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Preset(#[from] PresetError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("TOML serialization error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error("The playlist is empty")]
+    Empty,
+    #[error("Transition time must be at most {1} ms, got {0}")]
+    InvalidTransition(u32, f64),
+    #[error("Dwell time of \"{0}\" must be between {MIN_DWELL_SECS} and {MAX_DWELL_SECS} seconds, got {1}")]
+    InvalidDwell(String, u32),
+    #[error("Preset \"{0}\" is in the playlist; remove it from there first")]
+    PresetInUse(String),
+    #[error("None of the playlist's presets exist anymore")]
+    NothingToPlay,
+}
+
+/// A preset in the playlist and how long it stays on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub preset: String,
+    pub dwell_secs: u32,
+}
+
+/// Presets RainDesk cycles through on its own, in order or shuffled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Playlist {
+    pub shuffle: bool,
+    /// Transition between presets; the configured one when not set
+    pub transition_ms: Option<u32>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// Where playback is, saved so it carries over to the next launch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Position {
+    running: bool,
+    /// Index into `order` of the entry on screen
+    index: usize,
+    /// Play order as indices into the entries; reshuffled for every pass
+    /// when the playlist is shuffled
+    order: Vec<usize>,
+    /// When the current entry's dwell time is up, in Unix milliseconds.
+    /// Wall-clock time, so a deadline that passed while the app was closed
+    /// is noticed on launch.
+    ends_at_ms: Option<u64>,
+}
+
+/// Playlist and playback state, as shown in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistStatus {
+    pub playlist: Playlist,
+    pub running: bool,
+    /// Index into `playlist.entries` of the current preset
+    pub current: Option<usize>,
+    /// When the next preset is applied, in Unix milliseconds (running only)
+    pub ends_at_ms: Option<u64>,
+    /// Presets of the entries passed over on the last move because they no
+    /// longer exist
+    pub skipped: Vec<String>,
+}
+
+/// The playlist with its playback position. Changing the position returns
+/// the entry whose preset should be applied.
+pub struct PlaylistState {
+    playlist: Playlist,
+    position: Position,
+    skipped: Vec<String>,
+}
+
+/// File holding the playlist
+fn playlist_path() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("playlist.toml"))
+}
+
+/// File remembering the playback position across restarts
+fn position_path() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("playlist_position.toml"))
+}
+
+fn read_toml<T: Default + for<'de> Deserialize<'de>>(path: Result<PathBuf, ConfigError>) -> T {
+    let Ok(path) = path else {
+        return T::default();
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return T::default(),
+        Err(e) => {
+            eprintln!("[raindesk] Failed to read {}: {}", path.display(), e);
+            return T::default();
+        }
+    };
+    toml::from_str(&contents).unwrap_or_else(|e| {
+        eprintln!("[raindesk] Ignoring invalid {}: {}", path.display(), e);
+        T::default()
+    })
+}
+
+impl PlaylistState {
+    /// Load the playlist and the position saved by the last run
+    pub fn load() -> Self {
+        let mut state = Self {
+            playlist: read_toml(playlist_path()),
+            position: read_toml(position_path()),
+            skipped: Vec::new(),
+        };
+        state
+            .position
+            .fit(state.playlist.entries.len(), state.playlist.shuffle);
+        if state.playlist.entries.is_empty() || state.position.ends_at_ms.is_none() {
+            state.position.running = false;
+            state.position.ends_at_ms = None;
+        }
+        state
+    }
+
+    pub fn status(&self) -> PlaylistStatus {
+        PlaylistStatus {
+            playlist: self.playlist.clone(),
+            running: self.position.running,
+            current: self.position.current(),
+            ends_at_ms: self.position.ends_at_ms,
+            skipped: self.skipped.clone(),
+        }
+    }
+
+    /// Transition time to apply the playlist's presets with
+    pub fn transition_ms(&self) -> Option<u32> {
+        self.playlist.transition_ms
+    }
+
+    /// When the current preset's dwell time is up, while running
+    pub fn ends_at_ms(&self) -> Option<u64> {
+        self.position.ends_at_ms.filter(|_| self.position.running)
+    }
+
+    /// Replace the playlist. Names are checked against the presets and
+    /// stored as the presets spell them. Playback restarts from the first
+    /// entry; if it's running, that entry is returned.
+    pub fn set(&mut self, mut playlist: Playlist) -> Result<Option<PlaylistEntry>, PlaylistError> {
+        if let Some(ms) = playlist.transition_ms {
+            if f64::from(ms) > schema::TRANSITION_DURATION.max {
+                return Err(PlaylistError::InvalidTransition(
+                    ms,
+                    schema::TRANSITION_DURATION.max,
+                ));
+            }
+        }
+        for entry in &mut playlist.entries {
+            entry.preset = presets::find(&entry.preset)?.name;
+            if !(MIN_DWELL_SECS..=MAX_DWELL_SECS).contains(&entry.dwell_secs) {
+                return Err(PlaylistError::InvalidDwell(
+                    entry.preset.clone(),
+                    entry.dwell_secs,
+                ));
+            }
+        }
+        write_playlist(&playlist)?;
+        self.playlist = playlist;
+        self.position.order = new_order(self.playlist.entries.len(), self.playlist.shuffle, None);
+        self.position.index = 0;
+        self.skipped.clear();
+        if self.playlist.entries.is_empty() {
+            self.position.running = false;
+            self.position.ends_at_ms = None;
+            self.save_position();
+            return Ok(None);
+        }
+        if !self.position.running {
+            self.save_position();
+            return Ok(None);
+        }
+        self.enter_playable(true).map(Some)
+    }
+
+    /// Point the entries playing preset `old` at its new name `new`.
+    /// Returns whether any entry changed.
+    pub fn rename_preset(&mut self, old: &str, new: &str) -> Result<bool, PlaylistError> {
+        let mut playlist = self.playlist.clone();
+        let mut renamed = false;
+        for entry in &mut playlist.entries {
+            if presets::key(&entry.preset) == presets::key(old) {
+                entry.preset = new.to_string();
+                renamed = true;
+            }
+        }
+        if renamed {
+            write_playlist(&playlist)?;
+            self.playlist = playlist;
+        }
+        Ok(renamed)
+    }
+
+    /// Refuse to let preset `name` go while an entry still plays it
+    pub fn ensure_unused(&self, name: &str) -> Result<(), PlaylistError> {
+        match self
+            .playlist
+            .entries
+            .iter()
+            .find(|entry| presets::key(&entry.preset) == presets::key(name))
+        {
+            Some(entry) => Err(PlaylistError::PresetInUse(entry.preset.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Start or resume playback at the current entry
+    pub fn start(&mut self) -> Result<PlaylistEntry, PlaylistError> {
+        if self.playlist.entries.is_empty() {
+            return Err(PlaylistError::Empty);
+        }
+        self.position.running = true;
+        self.enter_playable(true)
+    }
+
+    /// Stop playback; the preset on screen stays
+    pub fn stop(&mut self) {
+        self.position.running = false;
+        self.position.ends_at_ms = None;
+        self.save_position();
+    }
+
+    /// Move to the next entry, or the previous one when `forward` is false.
+    /// Works while stopped too, without starting playback.
+    pub fn skip(&mut self, forward: bool) -> Result<PlaylistEntry, PlaylistError> {
+        if self.position.order.is_empty() {
+            return Err(PlaylistError::Empty);
+        }
+        self.position.step(forward, self.playlist.shuffle);
+        self.enter_playable(forward)
+    }
+
+    /// Enter the current entry, or if its preset no longer exists (deleted
+    /// outside the app), the first one after it in direction `forward` that
+    /// does. The entries passed over are reported in the status. With none
+    /// left to play, playback stops.
+    fn enter_playable(&mut self, forward: bool) -> Result<PlaylistEntry, PlaylistError> {
+        self.skipped.clear();
+        for _ in 0..self.position.order.len() {
+            let entry = self.current_entry();
+            match presets::find(&entry.preset) {
+                Err(PresetError::NotFound(_)) => {
+                    eprintln!(
+                        "[raindesk] Playlist skipped \"{}\": the preset no longer exists",
+                        entry.preset
+                    );
+                    self.skipped.push(entry.preset);
+                    self.position.step(forward, self.playlist.shuffle);
+                }
+                // Any other problem is reported when the preset is applied
+                _ => return Ok(self.enter_current()),
+            }
+        }
+        self.stop();
+        Err(PlaylistError::NothingToPlay)
+    }
+
+    fn current_entry(&self) -> PlaylistEntry {
+        self.playlist.entries[self.position.order[self.position.index]].clone()
+    }
+
+    /// Start the dwell time of the current entry and save the position
+    fn enter_current(&mut self) -> PlaylistEntry {
+        let entry = self.current_entry();
+        self.position.ends_at_ms = self
+            .position
+            .running
+            .then(|| now_ms() + u64::from(entry.dwell_secs) * 1000);
+        self.save_position();
+        entry
+    }
+
+    /// Persist the position; playback goes on even if it can't be saved
+    fn save_position(&self) {
+        if let Err(e) = self.write_position() {
+            eprintln!("[raindesk] Failed to save playlist position: {}", e);
+        }
+    }
+
+    fn write_position(&self) -> Result<(), PlaylistError> {
+        let contents = toml::to_string_pretty(&self.position)?;
+        config::write_atomically(&position_path()?, &contents)?;
+        Ok(())
+    }
+}
+
+impl Position {
+    /// Index into the entries of the one on screen
+    fn current(&self) -> Option<usize> {
+        self.order.get(self.index).copied()
+    }
+
+    /// Make a saved position fit a playlist of `len` entries. One saved for
+    /// an edited playlist is meaningless, so playback starts over.
+    fn fit(&mut self, len: usize, shuffle: bool) {
+        let mut order = self.order.clone();
+        order.sort_unstable();
+        if !order.into_iter().eq(0..len) || self.index >= len.max(1) {
+            self.order = new_order(len, shuffle, None);
+            self.index = 0;
+        }
+    }
+
+    /// Move to the next entry, or the previous one when `forward` is false,
+    /// wrapping around at either end
+    fn step(&mut self, forward: bool, shuffle: bool) {
+        let len = self.order.len();
+        if len == 0 {
+            return;
+        }
+        if forward {
+            self.index += 1;
+            if self.index >= len {
+                // A new pass; a shuffled one gets a fresh order
+                let last = self.order.last().copied();
+                self.order = new_order(len, shuffle, last);
+                self.index = 0;
+            }
+        } else {
+            self.index = self.index.checked_sub(1).unwrap_or(len - 1);
+        }
+    }
+}
+
+/// Play order for a pass over `len` entries: in sequence, or shuffled
+/// without starting on `previous`, the entry the last pass ended on
+fn new_order(len: usize, shuffle: bool, previous: Option<usize>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    if shuffle {
+        self::shuffle(&mut order);
+        if order.len() > 1 && order.first() == previous.as_ref() {
+            order.swap(0, 1);
+        }
+    }
+    order
+}
+
+fn write_playlist(playlist: &Playlist) -> Result<(), PlaylistError> {
+    let contents = toml::to_string_pretty(playlist)?;
+    config::write_atomically(&playlist_path()?, &contents)?;
+    Ok(())
+}
+
+/// Fisher-Yates shuffle seeded from the clock; good enough for a playlist
+fn shuffle(items: &mut [usize]) {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+        | 1;
+    for i in (1..items.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(index: usize, order: Vec<usize>) -> Position {
+        Position {
+            index,
+            order,
+            ..Position::default()
+        }
+    }
+
+    #[test]
+    fn next_and_previous_wrap_around() {
+        let mut position = at(2, vec![0, 1, 2]);
+        position.step(true, false);
+        assert_eq!(position.current(), Some(0));
+        position.step(false, false);
+        assert_eq!(position.current(), Some(2));
+        position.step(false, false);
+        assert_eq!(position.current(), Some(1));
+    }
+
+    #[test]
+    fn shuffled_passes_play_every_entry_once() {
+        let len = 7;
+        let mut position = at(0, new_order(len, true, None));
+        let mut last_of_pass = None;
+        for _ in 0..20 {
+            let mut pass = Vec::new();
+            for _ in 0..len {
+                pass.push(position.current().unwrap());
+                position.step(true, true);
+            }
+            // A new pass doesn't repeat the entry the last one ended on
+            assert_ne!(pass.first().copied(), last_of_pass);
+            last_of_pass = pass.last().copied();
+            pass.sort_unstable();
+            assert_eq!(pass, (0..len).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn position_past_the_end_starts_over() {
+        // Saved while the playlist had five entries; two were removed since
+        let mut position = at(4, vec![0, 1, 2, 3, 4]);
+        position.fit(3, false);
+        assert_eq!(position.order, [0, 1, 2]);
+        assert_eq!(position.current(), Some(0));
+
+        // An order that still fits but an index beyond it
+        let mut position = at(3, vec![2, 0, 1]);
+        position.fit(3, true);
+        assert_eq!(position.index, 0);
+
+        // A position that still fits is kept
+        let mut position = at(1, vec![2, 0, 1]);
+        position.fit(3, true);
+        assert_eq!(position.order, [2, 0, 1]);
+        assert_eq!(position.current(), Some(0));
+    }
+}
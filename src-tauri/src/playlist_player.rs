@@ -0,0 +1,65 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::clock;
+use crate::state::AppState;
+
+/// Event emitted to the UI with the playlist status after it moved on by
+/// itself; the config changed with it
+pub const PLAYLIST_CHANGED_EVENT: &str = "playlist-changed";
+
+/// Longest single wait. Deadlines are wall-clock times, and the monotonic
+/// clock behind the wait stops during suspend, so check back regularly.
+const MAX_WAIT: Duration = Duration::from_secs(15);
+
+/// Apply the next playlist preset on a dedicated thread whenever the
+/// current one's dwell time is up
+pub fn spawn(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    *app.state::<AppState>().playlist_tx.lock().unwrap() = Some(tx);
+    let spawned = thread::Builder::new()
+        .name("raindesk-playlist".to_string())
+        .spawn(move || run(&app, &rx));
+    if let Err(e) = spawned {
+        eprintln!("[raindesk] Playlist thread not started: {}", e);
+    }
+}
+
+/// Sleep until the dwell time is up or the playlist changes. A deadline that
+/// passed while the app was closed is handled right away.
+fn run(app: &AppHandle, rx: &mpsc::Receiver<()>) {
+    let state = app.state::<AppState>();
+    loop {
+        let ends_at_ms = state.playlist.lock().unwrap().ends_at_ms();
+        let woken = match ends_at_ms {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(ends_at_ms) => {
+                let left = Duration::from_millis(ends_at_ms.saturating_sub(clock::now_ms()));
+                if left.is_zero() {
+                    advance(app);
+                    continue;
+                }
+                rx.recv_timeout(left.min(MAX_WAIT))
+            }
+        };
+        match woken {
+            // Changed by a command, or time to look at the clock again
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn advance(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    // Entries whose preset is gone are passed over and listed in the
+    // status; what's left to fail is applying the preset, and the position
+    // has moved on regardless
+    if let Err(e) = state.skip_playlist(true) {
+        eprintln!("[raindesk] Playlist could not apply the next preset: {}", e);
+    }
+    let _ = app.emit(PLAYLIST_CHANGED_EVENT, state.playlist_status());
+}
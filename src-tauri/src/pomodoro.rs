@@ -1,4 +1,22 @@
+use crate::clock::Now;
+use crate::config::{self, ConfigError, RainConfig};
+use crate::schema::{self, RangeCheck};
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PomodoroError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("TOML serialization error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+}
 
 /// Pomodoro timer phases
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -28,6 +46,108 @@ impl Default for PomodoroStatus {
     }
 }
 
+/// Something that happened to the timer, as kept in the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PomodoroEventKind {
+    /// A phase started running, by hand or after the previous one ended
+    Start,
+    /// A paused phase started running again
+    Resume,
+    /// The phase ran out
+    Complete,
+    /// The phase was skipped before it ran out
+    Skip,
+    Pause,
+    Reset,
+}
+
+/// A timer event with its time and durations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PomodoroEvent {
+    /// When it happened, in Unix milliseconds
+    pub at_ms: u64,
+    pub kind: PomodoroEventKind,
+    /// Phase the event is about; for a start, the phase that began
+    pub phase: PomodoroPhase,
+    /// Full duration of the phase in seconds
+    pub phase_secs: u32,
+    /// Seconds the timer ran since it was last started or resumed, up to
+    /// this event (0 for starts and resumes)
+    pub ran_secs: u32,
+}
+
+/// What happens to a running timer when the computer wakes from suspend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResumePolicy {
+    /// Play out every phase that would have ended while asleep, as if the
+    /// timer had kept running
+    CatchUp,
+    /// Pause with the time that was left when the computer went to sleep
+    #[default]
+    Pause,
+    /// End the current phase if its time ran out, and start the next one
+    /// from the moment of waking
+    CompletePhase,
+}
+
+/// Pomodoro durations, persisted with the rest of the config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroSettings {
+    /// Work duration in seconds (default 25 min)
+    pub work_duration_secs: u32,
+    /// Short break duration in seconds (default 5 min)
+    pub short_break_duration_secs: u32,
+    /// Long break duration in seconds (default 15 min)
+    pub long_break_duration_secs: u32,
+    /// Number of work sessions before long break (default 4)
+    pub sessions_until_long_break: u32,
+    /// How a timer running across a suspend is handled
+    pub resume_policy: ResumePolicy,
+    /// Keep a timer that was running at exit running on the next launch,
+    /// instead of restoring it paused
+    pub resume_on_launch: bool,
+    /// Minutes of focus a day counts towards a streak (default 100)
+    pub daily_goal_mins: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_duration_secs: 25 * 60,
+            short_break_duration_secs: 5 * 60,
+            long_break_duration_secs: 15 * 60,
+            sessions_until_long_break: 4,
+            resume_policy: ResumePolicy::default(),
+            resume_on_launch: false,
+            daily_goal_mins: 100,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    /// Clamp durations to 1 minute - 3 hours, sessions to 1 - 12 and the
+    /// daily goal to 5 minutes - 12 hours
+    pub fn clamp(&mut self, check: &mut RangeCheck) {
+        check.number(&mut self.work_duration_secs, &schema::WORK_DURATION);
+        check.number(
+            &mut self.short_break_duration_secs,
+            &schema::SHORT_BREAK_DURATION,
+        );
+        check.number(
+            &mut self.long_break_duration_secs,
+            &schema::LONG_BREAK_DURATION,
+        );
+        check.number(
+            &mut self.sessions_until_long_break,
+            &schema::SESSIONS_UNTIL_LONG_BREAK,
+        );
+        check.number(&mut self.daily_goal_mins, &schema::DAILY_GOAL);
+    }
+}
+
 /// Pomodoro timer state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PomodoroState {
@@ -35,8 +155,12 @@ pub struct PomodoroState {
     pub phase: PomodoroPhase,
     /// Current status
     pub status: PomodoroStatus,
-    /// Remaining seconds in current phase
+    /// Remaining seconds in current phase; while running, as of the last update
     pub remaining_secs: u32,
+    /// When the current phase ends, in Unix milliseconds (running only).
+    /// The remaining time is worked out from the clock, so missed updates
+    /// and suspends don't stretch a phase.
+    pub deadline_ms: Option<u64>,
     /// Completed work sessions count
     pub completed_sessions: u32,
     /// Work duration in seconds (default 25 min)
@@ -47,8 +171,53 @@ pub struct PomodoroState {
     pub long_break_duration_secs: u32,
     /// Number of work sessions before long break (default 4)
     pub sessions_until_long_break: u32,
+    /// How a timer running across a suspend is handled
+    pub resume_policy: ResumePolicy,
+    /// Clocks at the last update while running, to notice a suspend or the
+    /// wall clock being set
+    #[serde(skip)]
+    last_update: Option<Now>,
+    /// When the timer was last started or resumed, or the running phase began
+    #[serde(skip)]
+    run_started_ms: Option<u64>,
+    /// Events not yet taken for the history
+    #[serde(skip)]
+    events: Vec<PomodoroEvent>,
+    /// When the timer was last saved, in Unix milliseconds
+    #[serde(skip)]
+    saved_at_ms: Option<u64>,
+}
+
+/// The timer as saved for the next launch; durations come from the config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedTimer {
+    phase: PomodoroPhase,
+    status: PomodoroStatus,
+    remaining_secs: u32,
+    deadline_ms: Option<u64>,
+    completed_sessions: u32,
+    run_started_ms: Option<u64>,
+    /// When it was saved, in Unix milliseconds
+    saved_at_ms: u64,
 }
 
+/// File remembering the timer across restarts
+fn saved_timer_path() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("pomodoro_state.toml"))
+}
+
+/// A running timer is saved at least this often, so after a crash it comes
+/// back with at most this much more time left than it really had
+const SAVE_INTERVAL_MS: u64 = 60_000;
+
+/// Shorter suspends are ignored; the timer just carries on
+const SUSPEND_GAP_MS: u64 = 5_000;
+
+/// Disagreements between the wall clock and the time since boot up to this
+/// are reading jitter or NTP slewing, not the clock being set
+const CLOCK_STEP_MS: i64 = 1_000;
+
 impl Default for PomodoroState {
     fn default() -> Self {
         Self::new()
@@ -57,61 +226,292 @@ impl Default for PomodoroState {
 
 impl PomodoroState {
     pub fn new() -> Self {
-        let work_duration = 25 * 60;
+        let settings = PomodoroSettings::default();
         Self {
             phase: PomodoroPhase::Work,
             status: PomodoroStatus::Idle,
-            remaining_secs: work_duration,
+            remaining_secs: settings.work_duration_secs,
+            deadline_ms: None,
             completed_sessions: 0,
-            work_duration_secs: work_duration,
-            short_break_duration_secs: 5 * 60,
-            long_break_duration_secs: 15 * 60,
-            sessions_until_long_break: 4,
+            work_duration_secs: settings.work_duration_secs,
+            short_break_duration_secs: settings.short_break_duration_secs,
+            long_break_duration_secs: settings.long_break_duration_secs,
+            sessions_until_long_break: settings.sessions_until_long_break,
+            resume_policy: settings.resume_policy,
+            last_update: None,
+            run_started_ms: None,
+            events: Vec::new(),
+            saved_at_ms: None,
+        }
+    }
+
+    /// Take over the timer saved by the last run, with `settings` from the
+    /// config. A timer that was running keeps its deadline if
+    /// `settings.resume_on_launch` is set; if the phase ran out while the
+    /// app was closed, the next one starts now. Otherwise it comes back
+    /// paused with the time it had left when it was last saved.
+    pub fn restore(settings: &PomodoroSettings, now: Now) -> Self {
+        Self::restore_from(read_saved_timer(), settings, now)
+    }
+
+    fn restore_from(saved: Option<SavedTimer>, settings: &PomodoroSettings, now: Now) -> Self {
+        let mut state = Self::new();
+        let mut paused_at = None;
+        if let Some(saved) = saved {
+            state.phase = saved.phase;
+            state.status = saved.status;
+            state.remaining_secs = saved.remaining_secs;
+            state.completed_sessions = saved.completed_sessions;
+            state.run_started_ms = saved.run_started_ms;
+            if saved.status == PomodoroStatus::Running {
+                state.deadline_ms = saved.deadline_ms;
+                state.last_update = Some(now);
+                if !settings.resume_on_launch || saved.deadline_ms.is_none() {
+                    paused_at = Some(saved.saved_at_ms);
+                }
+            }
+        }
+        // Durations may have changed since; apply them before catching up
+        state.apply_settings(settings, now);
+        if let Some(at) = paused_at {
+            state.freeze(at);
+        } else if let Some(deadline) = state
+            .deadline_ms
+            .filter(|deadline| now.wall_ms >= *deadline)
+        {
+            state.end_phase(deadline);
+            state.begin_run(now.wall_ms);
+        }
+        state
+    }
+
+    /// The timer as of `now`, to be saved for the next launch with
+    /// `SavedTimer::write` once the timer is no longer locked. Counts as
+    /// the latest save for `save_due`.
+    pub fn take_save(&mut self, now: Now) -> SavedTimer {
+        self.saved_at_ms = Some(now.wall_ms);
+        SavedTimer {
+            phase: self.phase,
+            status: self.status,
+            remaining_secs: self.remaining_secs,
+            deadline_ms: self.deadline_ms,
+            completed_sessions: self.completed_sessions,
+            run_started_ms: self.run_started_ms,
+            saved_at_ms: now.wall_ms,
+        }
+    }
+
+    /// Whether a running timer has gone `SAVE_INTERVAL_MS` without a save
+    pub fn save_due(&self, now: Now) -> bool {
+        self.status == PomodoroStatus::Running
+            && self
+                .saved_at_ms
+                .is_none_or(|at| at.abs_diff(now.wall_ms) >= SAVE_INTERVAL_MS)
+    }
+
+    /// Adopt new settings. An idle timer restarts the current phase with
+    /// the new duration; a running or paused one keeps its remaining time,
+    /// cut short if the phase got shorter.
+    pub fn apply_settings(&mut self, settings: &PomodoroSettings, now: Now) {
+        self.work_duration_secs = settings.work_duration_secs;
+        self.short_break_duration_secs = settings.short_break_duration_secs;
+        self.long_break_duration_secs = settings.long_break_duration_secs;
+        self.sessions_until_long_break = settings.sessions_until_long_break;
+        self.resume_policy = settings.resume_policy;
+        let phase_duration = self.phase_duration();
+        if self.status == PomodoroStatus::Idle {
+            self.remaining_secs = phase_duration;
+        } else {
+            self.remaining_secs = self.remaining_secs.min(phase_duration);
+        }
+        if let Some(deadline) = self.deadline_ms {
+            let deadline = deadline.min(now.wall_ms + u64::from(phase_duration) * 1000);
+            self.deadline_ms = Some(deadline);
+            self.remaining_secs = secs_left(deadline, now.wall_ms);
+        }
+    }
+
+    /// Full duration of the current phase in seconds
+    fn phase_duration(&self) -> u32 {
+        match self.phase {
+            PomodoroPhase::Work => self.work_duration_secs,
+            PomodoroPhase::ShortBreak => self.short_break_duration_secs,
+            PomodoroPhase::LongBreak => self.long_break_duration_secs,
         }
     }
 
     /// Start or resume the timer
-    pub fn start(&mut self) {
+    pub fn start(&mut self, now: Now) {
+        let kind = match self.status {
+            PomodoroStatus::Running => return,
+            PomodoroStatus::Paused => PomodoroEventKind::Resume,
+            PomodoroStatus::Idle => PomodoroEventKind::Start,
+        };
         self.status = PomodoroStatus::Running;
+        self.deadline_ms = Some(now.wall_ms + u64::from(self.remaining_secs) * 1000);
+        self.last_update = Some(now);
+        self.run_started_ms = Some(now.wall_ms);
+        self.record(kind, now.wall_ms);
     }
 
     /// Pause the timer
-    pub fn pause(&mut self) {
+    pub fn pause(&mut self, now: Now) {
         if self.status == PomodoroStatus::Running {
-            self.status = PomodoroStatus::Paused;
+            self.update(now);
+            self.freeze(now.wall_ms);
+        }
+    }
+
+    /// Stop a running clock with the time left at `at`
+    fn freeze(&mut self, at: u64) {
+        if self.status != PomodoroStatus::Running {
+            return;
+        }
+        if let Some(deadline) = self.deadline_ms.take() {
+            self.remaining_secs = secs_left(deadline, at);
         }
+        self.record(PomodoroEventKind::Pause, at);
+        self.status = PomodoroStatus::Paused;
+        self.run_started_ms = None;
     }
 
     /// Reset to initial state
-    pub fn reset(&mut self) {
+    pub fn reset(&mut self, now: Now) {
+        if self.status != PomodoroStatus::Idle || self.completed_sessions > 0 {
+            self.record(PomodoroEventKind::Reset, now.wall_ms);
+        }
         self.phase = PomodoroPhase::Work;
         self.status = PomodoroStatus::Idle;
         self.remaining_secs = self.work_duration_secs;
+        self.deadline_ms = None;
         self.completed_sessions = 0;
+        self.run_started_ms = None;
     }
 
     /// Skip to the next phase
-    pub fn skip_phase(&mut self) {
+    pub fn skip_phase(&mut self, now: Now) {
+        self.record(PomodoroEventKind::Skip, now.wall_ms);
         self.transition_to_next_phase();
+        if self.status == PomodoroStatus::Running {
+            self.begin_run(now.wall_ms);
+            self.last_update = Some(now);
+        }
     }
 
-    /// Tick the timer (call every second when running).
-    /// Returns the new phase if a transition occurred.
-    pub fn tick(&mut self) -> Option<PomodoroPhase> {
-        if self.status != PomodoroStatus::Running {
-            return None;
-        }
+    /// Events since the last call, oldest first, for the history
+    pub fn take_events(&mut self) -> Vec<PomodoroEvent> {
+        std::mem::take(&mut self.events)
+    }
 
-        if self.remaining_secs > 0 {
-            self.remaining_secs -= 1;
+    /// Bring a running timer up to `now`: refresh the remaining time and
+    /// end every phase whose deadline has passed. After a suspend the
+    /// resume policy decides what happens instead; when the wall clock was
+    /// set, the deadline moves with it. Returns the phases that began,
+    /// oldest first.
+    pub fn update(&mut self, now: Now) -> Vec<PomodoroPhase> {
+        let mut begun = Vec::new();
+        let Some(mut deadline) = self
+            .deadline_ms
+            .filter(|_| self.status == PomodoroStatus::Running)
+        else {
+            return begun;
+        };
+        let mut asleep_since = None;
+        if let Some(last) = self.last_update {
+            // The monotonic clock stops during suspend, the time since boot
+            // doesn't, and neither follows the wall clock being set
+            let awake_ms = millis(now.awake.saturating_duration_since(last.awake));
+            let since_boot_ms = millis(now.since_boot.saturating_sub(last.since_boot));
+            let wall_elapsed_ms = i128::from(now.wall_ms) - i128::from(last.wall_ms);
+            let step_ms = i64::try_from(wall_elapsed_ms - i128::from(since_boot_ms)).unwrap_or(0);
+            if step_ms.abs() > CLOCK_STEP_MS {
+                deadline = deadline.saturating_add_signed(step_ms);
+                self.run_started_ms = self
+                    .run_started_ms
+                    .map(|started| started.saturating_add_signed(step_ms));
+            }
+            let slept_ms = since_boot_ms.saturating_sub(awake_ms);
+            if slept_ms > SUSPEND_GAP_MS {
+                asleep_since = Some(now.wall_ms.saturating_sub(slept_ms));
+            }
         }
+        self.last_update = Some(now);
+        // A phase never has more than its full duration left
+        deadline = deadline.min(now.wall_ms + u64::from(self.phase_duration()) * 1000);
 
-        if self.remaining_secs == 0 {
-            self.transition_to_next_phase();
-            Some(self.phase)
+        // With the pause policy, the clock stopped when the computer went to sleep
+        let until = match (asleep_since, self.resume_policy) {
+            (Some(asleep_since), ResumePolicy::Pause) => asleep_since,
+            _ => now.wall_ms,
+        };
+        if asleep_since.is_some()
+            && self.resume_policy == ResumePolicy::CompletePhase
+            && now.wall_ms >= deadline
+        {
+            // The next phase starts on waking, however long the sleep was
+            self.end_phase(deadline);
+            begun.push(self.phase);
+            deadline = self.begin_run(now.wall_ms);
+        }
+        // Phases run back to back, each from the previous deadline
+        while until >= deadline {
+            self.end_phase(deadline);
+            begun.push(self.phase);
+            deadline = self.begin_run(deadline);
+        }
+        self.deadline_ms = Some(deadline);
+        if until < now.wall_ms {
+            self.freeze(until);
         } else {
-            None
+            self.remaining_secs = secs_left(deadline, now.wall_ms);
         }
+        begun
+    }
+
+    /// Milliseconds until the displayed remaining time next changes, while
+    /// running
+    pub fn next_update_in(&self, now: Now) -> Option<u64> {
+        let deadline = self
+            .deadline_ms
+            .filter(|_| self.status == PomodoroStatus::Running)?;
+        match deadline.saturating_sub(now.wall_ms) % 1000 {
+            0 => Some(if deadline <= now.wall_ms { 0 } else { 1000 }),
+            ms => Some(ms),
+        }
+    }
+
+    /// Complete the running phase at `at` and move on to the next one
+    fn end_phase(&mut self, at: u64) {
+        self.record(PomodoroEventKind::Complete, at);
+        self.transition_to_next_phase();
+    }
+
+    /// Start running the current phase from its full duration at `at`.
+    /// Returns the new deadline.
+    fn begin_run(&mut self, at: u64) -> u64 {
+        let deadline = at + u64::from(self.remaining_secs) * 1000;
+        self.deadline_ms = Some(deadline);
+        self.run_started_ms = Some(at);
+        self.record(PomodoroEventKind::Start, at);
+        deadline
+    }
+
+    /// Note an event about the current phase for the history
+    fn record(&mut self, kind: PomodoroEventKind, at_ms: u64) {
+        let ran_ms = match kind {
+            PomodoroEventKind::Start | PomodoroEventKind::Resume => 0,
+            _ => self
+                .run_started_ms
+                .filter(|_| self.status == PomodoroStatus::Running)
+                .map_or(0, |started| at_ms.saturating_sub(started)),
+        };
+        self.events.push(PomodoroEvent {
+            at_ms,
+            kind,
+            phase: self.phase,
+            phase_secs: self.phase_duration(),
+            ran_secs: ((ran_ms + 500) / 1000) as u32,
+        });
     }
 
     /// Transition to the next phase
@@ -152,3 +552,233 @@ impl PomodoroState {
         }
     }
 }
+
+impl SavedTimer {
+    /// Save it so the next launch can pick it up; the timer goes on even if
+    /// it can't be saved
+    pub fn write(&self) {
+        if let Err(e) = write_saved_timer(self) {
+            eprintln!("[raindesk] Failed to save pomodoro timer: {}", e);
+        }
+    }
+}
+
+fn write_saved_timer(saved: &SavedTimer) -> Result<(), PomodoroError> {
+    let contents = toml::to_string_pretty(saved)?;
+    config::write_atomically(&saved_timer_path()?, &contents)?;
+    Ok(())
+}
+
+/// The timer saved by the last run, if there is a readable one
+fn read_saved_timer() -> Option<SavedTimer> {
+    let path = saved_timer_path().ok()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            eprintln!("[raindesk] Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    toml::from_str(&contents)
+        .map_err(|e| eprintln!("[raindesk] Ignoring invalid {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Whole milliseconds in `duration`
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Whole seconds from `now_ms` until `deadline_ms`, rounded up
+fn secs_left(deadline_ms: u64, now_ms: u64) -> u32 {
+    deadline_ms.saturating_sub(now_ms).div_ceil(1000) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const MINUTE_MS: u64 = 60_000;
+
+    /// Readings of the three clocks that tests move by hand
+    struct TestClock(Now);
+
+    impl TestClock {
+        fn new() -> Self {
+            Self(Now {
+                wall_ms: 1_760_000_000_000,
+                awake: Instant::now(),
+                since_boot: Duration::from_secs(3600),
+            })
+        }
+
+        /// The computer is on: every clock moves
+        fn run(&mut self, ms: u64) -> Now {
+            self.0.wall_ms += ms;
+            self.0.awake += Duration::from_millis(ms);
+            self.0.since_boot += Duration::from_millis(ms);
+            self.0
+        }
+
+        /// The computer is asleep: the monotonic clock stands still
+        fn sleep(&mut self, ms: u64) -> Now {
+            self.0.wall_ms += ms;
+            self.0.since_boot += Duration::from_millis(ms);
+            self.0
+        }
+
+        /// The wall clock is set, without any time passing
+        fn step(&mut self, ms: i64) -> Now {
+            self.0.wall_ms = self.0.wall_ms.saturating_add_signed(ms);
+            self.0
+        }
+    }
+
+    fn running(policy: ResumePolicy, clock: &TestClock) -> PomodoroState {
+        let settings = PomodoroSettings {
+            resume_policy: policy,
+            ..PomodoroSettings::default()
+        };
+        let mut state = PomodoroState::new();
+        state.apply_settings(&settings, clock.0);
+        state.start(clock.0);
+        state
+    }
+
+    fn kinds(state: &mut PomodoroState) -> Vec<PomodoroEventKind> {
+        state
+            .take_events()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect()
+    }
+
+    #[test]
+    fn timer_killed_mid_phase_restores_paused_near_the_crash() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        let mut saved = None;
+        // Ticks as the clock thread makes them, until the app is killed
+        for _ in 0..(10 * 60 + 30) {
+            let now = clock.run(1000);
+            state.update(now);
+            if state.save_due(now) {
+                saved = Some(state.take_save(now));
+            }
+        }
+
+        let restored = PomodoroState::restore_from(
+            saved,
+            &PomodoroSettings::default(),
+            clock.run(5 * MINUTE_MS),
+        );
+        assert_eq!(restored.status, PomodoroStatus::Paused);
+        // 14:30 were left at the crash; at most a save interval is given back
+        let left_at_crash = 14 * 60 + 30;
+        assert!(restored.remaining_secs >= left_at_crash);
+        assert!(restored.remaining_secs <= left_at_crash + (SAVE_INTERVAL_MS / 1000) as u32);
+    }
+
+    #[test]
+    fn pause_policy_stops_the_timer_when_the_computer_slept() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+        let asleep_at = clock.0.wall_ms;
+
+        assert!(state.update(clock.sleep(30 * MINUTE_MS)).is_empty());
+        assert_eq!(state.status, PomodoroStatus::Paused);
+        assert_eq!(state.phase, PomodoroPhase::Work);
+        assert_eq!(state.remaining_secs, 15 * 60);
+        let events = state.take_events();
+        assert_eq!(events.last().unwrap().kind, PomodoroEventKind::Pause);
+        assert_eq!(events.last().unwrap().at_ms, asleep_at);
+    }
+
+    #[test]
+    fn catch_up_policy_runs_the_phases_missed_while_asleep() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::CatchUp, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+
+        let begun = state.update(clock.sleep(30 * MINUTE_MS));
+        assert_eq!(begun, [PomodoroPhase::ShortBreak, PomodoroPhase::Work]);
+        assert_eq!(state.status, PomodoroStatus::Running);
+        assert_eq!(state.completed_sessions, 1);
+        // The second work session began 30 minutes after the first
+        assert_eq!(state.remaining_secs, 15 * 60);
+    }
+
+    #[test]
+    fn complete_phase_policy_starts_the_next_phase_on_waking() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::CompletePhase, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+
+        let started_at = clock.0.wall_ms - 10 * MINUTE_MS;
+
+        let begun = state.update(clock.sleep(30 * MINUTE_MS));
+        assert_eq!(begun, [PomodoroPhase::ShortBreak]);
+        assert_eq!(state.status, PomodoroStatus::Running);
+        assert_eq!(state.remaining_secs, 5 * 60);
+        let events = state.take_events();
+        // The work session ended at its deadline, the break began on waking
+        assert_eq!(events[1].kind, PomodoroEventKind::Complete);
+        assert_eq!(events[1].at_ms, started_at + 25 * MINUTE_MS);
+        assert_eq!(events[2].kind, PomodoroEventKind::Start);
+        assert_eq!(events[2].at_ms, clock.0.wall_ms);
+    }
+
+    #[test]
+    fn short_sleep_is_not_a_suspend() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+
+        state.update(clock.sleep(SUSPEND_GAP_MS));
+        assert_eq!(state.status, PomodoroStatus::Running);
+    }
+
+    #[test]
+    fn forward_clock_step_moves_the_deadline() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+
+        clock.step(2 * 60 * MINUTE_MS as i64);
+        assert!(state.update(clock.run(1000)).is_empty());
+        assert_eq!(state.status, PomodoroStatus::Running);
+        assert_eq!(state.remaining_secs, 15 * 60 - 1);
+        assert_eq!(kinds(&mut state), [PomodoroEventKind::Start]);
+    }
+
+    #[test]
+    fn backward_clock_step_moves_the_deadline() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        state.update(clock.run(10 * MINUTE_MS));
+
+        clock.step(-60 * MINUTE_MS as i64);
+        assert!(state.update(clock.run(1000)).is_empty());
+        assert_eq!(state.status, PomodoroStatus::Running);
+        assert_eq!(state.remaining_secs, 15 * 60 - 1);
+
+        // The work session still ends 25 minutes after it started
+        assert!(state.update(clock.run(15 * MINUTE_MS - 2000)).is_empty());
+        assert_eq!(state.update(clock.run(1000)), [PomodoroPhase::ShortBreak]);
+    }
+
+    #[test]
+    fn remaining_time_never_exceeds_the_phase() {
+        let mut clock = TestClock::new();
+        let mut state = running(ResumePolicy::Pause, &clock);
+        // Without an earlier reading a step can't be told apart, as after
+        // a restart
+        state.last_update = None;
+
+        state.update(clock.step(-60 * MINUTE_MS as i64));
+        assert_eq!(state.remaining_secs, 25 * 60);
+    }
+}
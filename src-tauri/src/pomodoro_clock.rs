@@ -0,0 +1,80 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::clock;
+use crate::pomodoro::{PomodoroPhase, PomodoroStatus};
+use crate::state::AppState;
+
+/// Event emitted to the UI with the pomodoro state after every tick
+pub const POMODORO_CHANGED_EVENT: &str = "pomodoro-changed";
+
+/// Drive the pomodoro timer from a dedicated thread, so it keeps time while
+/// the window is hidden, closed to the tray, or throttled by the webview
+pub fn spawn(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    *app.state::<AppState>().pomodoro_tx.lock().unwrap() = Some(tx);
+    let spawned = thread::Builder::new()
+        .name("raindesk-pomodoro".to_string())
+        .spawn(move || run(&app, &rx));
+    if let Err(e) = spawned {
+        eprintln!("[raindesk] Pomodoro clock not started: {}", e);
+    }
+}
+
+/// While the timer runs, update it whenever the displayed second changes;
+/// otherwise sleep until a command wakes the clock. Waits are at most a
+/// second, so a suspend or the wall clock being set is noticed on the next
+/// update.
+fn run(app: &AppHandle, rx: &mpsc::Receiver<()>) {
+    let state = app.state::<AppState>();
+    loop {
+        let wait = state.pomodoro.lock().unwrap().next_update_in(clock::now());
+        let woken = match wait {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(ms) => rx.recv_timeout(Duration::from_millis(ms)),
+        };
+        match woken {
+            // Started, paused, skipped, ...; look at the status again
+            Ok(()) => {}
+            Err(RecvTimeoutError::Timeout) => tick(app),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn tick(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let now = clock::now();
+    let mut pomodoro = state.pomodoro.lock().unwrap();
+    // Several phases may have ended at once (catching up after a suspend);
+    // only the one now running is worth a notification
+    let begun = pomodoro.update(now);
+    if let Some(&new_phase) = begun.last() {
+        notify_phase(new_phase);
+    }
+    // Saved now and then while running too, so a crash loses little time
+    let save =
+        !begun.is_empty() || pomodoro.status != PomodoroStatus::Running || pomodoro.save_due(now);
+    let snapshot = state.persist_pomodoro(pomodoro, save.then_some(now));
+    let _ = app.emit(POMODORO_CHANGED_EVENT, snapshot);
+}
+
+/// Show a desktop notification for the phase that just began
+fn notify_phase(phase: PomodoroPhase) {
+    let (summary, body) = match phase {
+        PomodoroPhase::Work => ("Back to Work!", "Focus time has started."),
+        PomodoroPhase::ShortBreak => ("Short Break", "Take a quick breather."),
+        PomodoroPhase::LongBreak => ("Long Break", "Great job! Take a longer rest."),
+    };
+    thread::spawn(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("RainDesk")
+            .summary(summary)
+            .body(body)
+            .timeout(5000)
+            .show();
+    });
+}
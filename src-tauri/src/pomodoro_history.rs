@@ -0,0 +1,113 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::pomodoro::PomodoroEvent;
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("Failed to get data directory")]
+    NoDataDir,
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Range start {0} is after its end {1}")]
+    InvalidRange(u64, u64),
+}
+
+/// Directory for data RainDesk collects, as opposed to settings
+fn data_dir() -> Result<PathBuf, HistoryError> {
+    let proj_dirs = directories::ProjectDirs::from("com", "danielhe4rt", "raindesk")
+        .ok_or(HistoryError::NoDataDir)?;
+    Ok(proj_dirs.data_dir().to_path_buf())
+}
+
+/// Log of timer events, one JSON object per line, oldest first
+fn history_path() -> Result<PathBuf, HistoryError> {
+    Ok(data_dir()?.join("pomodoro_history.jsonl"))
+}
+
+/// Append events to the history; the timer goes on even if they can't be
+/// written
+pub fn record(events: &[PomodoroEvent]) {
+    if events.is_empty() {
+        return;
+    }
+    if let Err(e) = append(events) {
+        eprintln!("[raindesk] Failed to write pomodoro history: {}", e);
+    }
+}
+
+fn append(events: &[PomodoroEvent]) -> Result<(), HistoryError> {
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event)?);
+        lines.push('\n');
+    }
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    // An earlier append cut short leaves a line without its newline; end it
+    // so these events start on a line of their own. Only the torn line is
+    // lost, and `query` skips it.
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            lines.insert(0, '\n');
+        }
+    }
+    file.write_all(lines.as_bytes())?;
+    // Recorded events survive a crash
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Events from `from_ms` (inclusive) to `to_ms` (exclusive), in Unix
+/// milliseconds, oldest first. Either end can be left open.
+pub fn query(from_ms: Option<u64>, to_ms: Option<u64>) -> Result<Vec<PomodoroEvent>, HistoryError> {
+    let from_ms = from_ms.unwrap_or(0);
+    let to_ms = to_ms.unwrap_or(u64::MAX);
+    if from_ms > to_ms {
+        return Err(HistoryError::InvalidRange(from_ms, to_ms));
+    }
+    let path = history_path()?;
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut events = Vec::new();
+    let mut invalid = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<PomodoroEvent>(&line) {
+            Ok(event) if (from_ms..to_ms).contains(&event.at_ms) => events.push(event),
+            Ok(_) => {}
+            Err(_) => invalid += 1,
+        }
+    }
+    if invalid > 0 {
+        eprintln!(
+            "[raindesk] Skipped {} unreadable lines in {}",
+            invalid,
+            path.display()
+        );
+    }
+    // The wall clock can be set back, so sort rather than trust the file order
+    events.sort_by_key(|event| event.at_ms);
+    Ok(events)
+}
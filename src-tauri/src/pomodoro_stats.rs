@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::pomodoro::{PomodoroEvent, PomodoroEventKind, PomodoroPhase};
+use crate::pomodoro_history::{self, HistoryError};
+use crate::schema;
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+/// Offsets of real time zones lie between UTC-12 and UTC+14
+const MIN_UTC_OFFSET_MINS: i32 = -12 * 60;
+const MAX_UTC_OFFSET_MINS: i32 = 14 * 60;
+
+/// Longest range the stats cover, in days (about ten years)
+const MAX_RANGE_DAYS: i64 = 366 * 10;
+
+/// Longest a single run can last: a whole work phase of the longest allowed
+/// duration. Events this long after the range can still end a run in it.
+const MAX_RUN_MS: u64 = schema::WORK_DURATION.max as u64 * 1000;
+
+#[derive(Error, Debug)]
+pub enum StatsError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error("UTC offset must be between {MIN_UTC_OFFSET_MINS} and {MAX_UTC_OFFSET_MINS} minutes, got {0}")]
+    InvalidOffset(i32),
+    #[error("Timestamp {0} is out of range")]
+    InvalidTimestamp(u64),
+    #[error("Range start {0} is after its end {1}")]
+    InvalidRange(u64, u64),
+    #[error("Range spans {0} days; at most {MAX_RANGE_DAYS} are supported")]
+    RangeTooLong(i64),
+}
+
+/// Focus time and sessions of one local day
+#[derive(Debug, Clone, Serialize)]
+pub struct DayStats {
+    /// Local date, "YYYY-MM-DD"
+    pub date: String,
+    pub focus_secs: u32,
+    pub completed_sessions: u32,
+    pub skipped_sessions: u32,
+    /// Whether the focus time reached the daily goal
+    pub goal_met: bool,
+}
+
+/// Focus time and sessions of one week, Monday to Sunday
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekStats {
+    /// Local date of the Monday, "YYYY-MM-DD"
+    pub week_start: String,
+    pub focus_secs: u32,
+    pub completed_sessions: u32,
+    pub skipped_sessions: u32,
+}
+
+/// Statistics over a range of the pomodoro history. Focus time is the time
+/// the timer ran in work phases; a run still going is counted once it is
+/// paused, completed, skipped or reset.
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusStats {
+    /// Every day of the range, oldest first, including days without focus
+    pub days: Vec<DayStats>,
+    /// Every week touched by the range, oldest first
+    pub weeks: Vec<WeekStats>,
+    pub focus_secs: u32,
+    /// Work phases that ran out
+    pub completed_sessions: u32,
+    /// Work phases skipped before they ran out
+    pub skipped_sessions: u32,
+    /// Average time from a pause to the resume or reset that ended it
+    pub average_pause_secs: Option<u32>,
+    pub daily_goal_mins: u32,
+    /// Most consecutive days within the range that met the daily goal
+    pub longest_streak_days: u32,
+    /// Focus seconds by local weekday (Monday first) and hour of the day
+    pub hourly_focus_secs: [[u32; 24]; 7],
+}
+
+/// Totals of a day, while adding up
+#[derive(Default)]
+struct DayTotals {
+    focus_ms: i64,
+    completed_sessions: u32,
+    skipped_sessions: u32,
+}
+
+/// Statistics for the history from `from_ms` (inclusive) to `to_ms`
+/// (exclusive, or up to `now_ms`). Days start at local midnight for a zone
+/// `utc_offset_mins` ahead of UTC.
+pub fn focus_stats(
+    from_ms: Option<u64>,
+    to_ms: Option<u64>,
+    utc_offset_mins: i32,
+    daily_goal_mins: u32,
+    now_ms: u64,
+) -> Result<FocusStats, StatsError> {
+    if !(MIN_UTC_OFFSET_MINS..=MAX_UTC_OFFSET_MINS).contains(&utc_offset_mins) {
+        return Err(StatsError::InvalidOffset(utc_offset_mins));
+    }
+    let to_ms = to_ms.unwrap_or(now_ms.saturating_add(1));
+    if let Some(from_ms) = from_ms.filter(|from_ms| *from_ms > to_ms) {
+        return Err(StatsError::InvalidRange(from_ms, to_ms));
+    }
+    let range = Range {
+        from_ms: from_ms.map(timestamp).transpose()?,
+        to_ms: timestamp(to_ms)?,
+        offset_ms: i64::from(utc_offset_mins) * 60 * 1000,
+    };
+    // Runs that started in the range and ended after it count up to its end
+    let events = pomodoro_history::query(from_ms, Some(to_ms.saturating_add(MAX_RUN_MS)))?;
+    compute(&events, &range, daily_goal_mins)
+}
+
+/// Unix milliseconds as a signed number for the date math
+fn timestamp(ms: u64) -> Result<i64, StatsError> {
+    i64::try_from(ms).map_err(|_| StatsError::InvalidTimestamp(ms))
+}
+
+/// Time range of the stats, in Unix milliseconds, and the local zone
+struct Range {
+    from_ms: Option<i64>,
+    to_ms: i64,
+    offset_ms: i64,
+}
+
+impl Range {
+    /// Milliseconds since 1970-01-01 00:00 local time
+    fn local(&self, ms: i64) -> i64 {
+        ms.saturating_add(self.offset_ms)
+    }
+
+    fn local_day(&self, ms: i64) -> i64 {
+        self.local(ms).div_euclid(DAY_MS)
+    }
+}
+
+fn compute(
+    events: &[PomodoroEvent],
+    range: &Range,
+    daily_goal_mins: u32,
+) -> Result<FocusStats, StatsError> {
+    let mut days: BTreeMap<i64, DayTotals> = BTreeMap::new();
+    let mut hourly_ms = [[0i64; 24]; 7];
+    let mut pauses_ms = Vec::new();
+    let mut paused_at = None;
+
+    for event in events {
+        let Ok(at_ms) = i64::try_from(event.at_ms) else {
+            continue;
+        };
+        let in_range = at_ms < range.to_ms;
+        let day = range.local_day(at_ms);
+        let is_work = event.phase == PomodoroPhase::Work;
+        match event.kind {
+            PomodoroEventKind::Complete if is_work && in_range => {
+                days.entry(day).or_default().completed_sessions += 1;
+            }
+            PomodoroEventKind::Skip if is_work && in_range => {
+                days.entry(day).or_default().skipped_sessions += 1;
+            }
+            _ => {}
+        }
+        match event.kind {
+            PomodoroEventKind::Pause => paused_at = Some(at_ms),
+            PomodoroEventKind::Resume | PomodoroEventKind::Reset if in_range => {
+                if let Some(paused_at) = paused_at.take() {
+                    pauses_ms.push(at_ms - paused_at);
+                }
+            }
+            _ => {}
+        }
+
+        // The run ending here, clipped to the range, split at every hour
+        if !is_work || event.ran_secs == 0 {
+            continue;
+        }
+        let run_start = at_ms - i64::from(event.ran_secs) * 1000;
+        let mut start = range.local(run_start.max(range.from_ms.unwrap_or(i64::MIN)));
+        let end = range.local(at_ms.min(range.to_ms));
+        while start < end {
+            let piece_end = end.min((start.div_euclid(HOUR_MS) + 1) * HOUR_MS);
+            let piece_ms = piece_end - start;
+            let day = start.div_euclid(DAY_MS);
+            let hour = start.rem_euclid(DAY_MS) / HOUR_MS;
+            days.entry(day).or_default().focus_ms += piece_ms;
+            hourly_ms[weekday(day)][hour as usize] += piece_ms;
+            start = piece_end;
+        }
+    }
+
+    // Fill in the days without any focus, so the days are contiguous
+    let first_day = range
+        .from_ms
+        .map(|ms| range.local_day(ms))
+        .or_else(|| days.keys().next().copied());
+    let last_day = range.local_day(range.to_ms - 1);
+    if let Some(first_day) = first_day {
+        let span = last_day - first_day + 1;
+        if span > MAX_RANGE_DAYS {
+            return Err(StatsError::RangeTooLong(span));
+        }
+        for day in first_day..=last_day {
+            days.entry(day).or_default();
+        }
+    }
+
+    let goal_ms = i64::from(daily_goal_mins) * 60 * 1000;
+    let mut stats = FocusStats {
+        days: Vec::with_capacity(days.len()),
+        weeks: Vec::new(),
+        focus_secs: 0,
+        completed_sessions: 0,
+        skipped_sessions: 0,
+        average_pause_secs: match i64::try_from(pauses_ms.len()) {
+            Ok(0) | Err(_) => None,
+            Ok(n) => Some(secs(pauses_ms.iter().sum::<i64>() / n)),
+        },
+        daily_goal_mins,
+        longest_streak_days: 0,
+        hourly_focus_secs: hourly_ms.map(|hours| hours.map(secs)),
+    };
+    let mut streak = 0;
+    let mut week: Option<(i64, DayTotals)> = None;
+    for (&day, totals) in &days {
+        let goal_met = totals.focus_ms >= goal_ms;
+        streak = if goal_met { streak + 1 } else { 0 };
+        stats.longest_streak_days = stats.longest_streak_days.max(streak);
+        stats.days.push(DayStats {
+            date: civil_date(day),
+            focus_secs: secs(totals.focus_ms),
+            completed_sessions: totals.completed_sessions,
+            skipped_sessions: totals.skipped_sessions,
+            goal_met,
+        });
+        stats.completed_sessions += totals.completed_sessions;
+        stats.skipped_sessions += totals.skipped_sessions;
+
+        let monday = day - weekday(day) as i64;
+        if week.as_ref().is_some_and(|(start, _)| *start != monday) {
+            stats.weeks.extend(week.take().map(week_stats));
+        }
+        let (_, week_totals) = week.get_or_insert_with(|| (monday, DayTotals::default()));
+        week_totals.focus_ms += totals.focus_ms;
+        week_totals.completed_sessions += totals.completed_sessions;
+        week_totals.skipped_sessions += totals.skipped_sessions;
+    }
+    stats.weeks.extend(week.map(week_stats));
+    stats.focus_secs = stats.days.iter().map(|day| day.focus_secs).sum();
+    Ok(stats)
+}
+
+fn week_stats((monday, totals): (i64, DayTotals)) -> WeekStats {
+    WeekStats {
+        week_start: civil_date(monday),
+        focus_secs: secs(totals.focus_ms),
+        completed_sessions: totals.completed_sessions,
+        skipped_sessions: totals.skipped_sessions,
+    }
+}
+
+/// Milliseconds to whole seconds, rounded
+fn secs(ms: i64) -> u32 {
+    ((ms + 500) / 1000) as u32
+}
+
+/// Day of the week of a day counted from 1970-01-01, Monday being 0
+fn weekday(day: i64) -> usize {
+    // 1970-01-01 was a Thursday
+    (day + 3).rem_euclid(7) as usize
+}
+
+/// "YYYY-MM-DD" of a day counted from 1970-01-01 (proleptic Gregorian)
+fn civil_date(day: i64) -> String {
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day_of_month = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-12, a Monday
+    const MONDAY: i64 = 20_738;
+    const UTC_PLUS_2: i64 = 2 * HOUR_MS;
+
+    fn work_run(kind: PomodoroEventKind, end_ms: i64, ran_secs: u32) -> PomodoroEvent {
+        PomodoroEvent {
+            at_ms: u64::try_from(end_ms).unwrap(),
+            kind,
+            phase: PomodoroPhase::Work,
+            phase_secs: 25 * 60,
+            ran_secs,
+        }
+    }
+
+    /// Range over `days` local days from `first_day` in UTC+2
+    fn local_days(first_day: i64, days: i64) -> Range {
+        Range {
+            from_ms: Some(first_day * DAY_MS - UTC_PLUS_2),
+            to_ms: (first_day + days) * DAY_MS - UTC_PLUS_2,
+            offset_ms: UTC_PLUS_2,
+        }
+    }
+
+    #[test]
+    fn civil_date_around_the_epoch() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(-1), "1969-12-31");
+        assert_eq!(civil_date(MONDAY), "2026-10-12");
+    }
+
+    #[test]
+    fn civil_date_leap_days() {
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(19_782), "2024-02-29");
+        assert_eq!(civil_date(-135_081), "1600-02-29");
+        // 1900 was not a leap year
+        assert_eq!(civil_date(-25_509), "1900-02-28");
+        assert_eq!(civil_date(-25_508), "1900-03-01");
+    }
+
+    #[test]
+    fn weekday_counts_from_monday() {
+        assert_eq!(weekday(0), 3);
+        assert_eq!(weekday(-1), 2);
+        assert_eq!(weekday(MONDAY), 0);
+        assert_eq!(weekday(MONDAY + 6), 6);
+        assert_eq!(weekday(MONDAY + 7), 0);
+    }
+
+    #[test]
+    fn run_across_local_midnight_is_split() {
+        // 23:30 to 00:30 local time in UTC+2, ending on Tuesday
+        let end = (MONDAY + 1) * DAY_MS + 30 * 60 * 1000 - UTC_PLUS_2;
+        let events = [work_run(PomodoroEventKind::Pause, end, 60 * 60)];
+        let stats = compute(&events, &local_days(MONDAY, 2), 100).unwrap();
+        let focus: Vec<_> = stats
+            .days
+            .iter()
+            .map(|d| (d.date.as_str(), d.focus_secs))
+            .collect();
+        assert_eq!(focus, [("2026-10-12", 1800), ("2026-10-13", 1800)]);
+        assert_eq!(stats.hourly_focus_secs[0][23], 1800);
+        assert_eq!(stats.hourly_focus_secs[1][0], 1800);
+        assert_eq!(stats.focus_secs, 3600);
+    }
+
+    #[test]
+    fn run_is_clipped_to_the_range() {
+        // 23:30 to 00:30 local time, with the range ending at midnight
+        let end = (MONDAY + 1) * DAY_MS + 30 * 60 * 1000 - UTC_PLUS_2;
+        let events = [work_run(PomodoroEventKind::Complete, end, 60 * 60)];
+        let stats = compute(&events, &local_days(MONDAY, 1), 100).unwrap();
+        assert_eq!(stats.days.len(), 1);
+        assert_eq!(stats.focus_secs, 1800);
+        // Completed after the range ended
+        assert_eq!(stats.completed_sessions, 0);
+
+        let stats = compute(&events, &local_days(MONDAY + 1, 1), 100).unwrap();
+        assert_eq!(stats.focus_secs, 1800);
+        assert_eq!(stats.completed_sessions, 1);
+    }
+
+    #[test]
+    fn streak_resets_on_a_missed_day() {
+        // Noon local time; 50 minutes meets the goal, 10 doesn't
+        let noon = |day: i64| day * DAY_MS + 12 * HOUR_MS - UTC_PLUS_2;
+        let events: Vec<_> = [50, 50, 10, 50, 50, 50]
+            .iter()
+            .enumerate()
+            .map(|(i, mins)| {
+                work_run(
+                    PomodoroEventKind::Complete,
+                    noon(MONDAY + i as i64),
+                    mins * 60,
+                )
+            })
+            .collect();
+        let stats = compute(&events, &local_days(MONDAY, 7), 50).unwrap();
+        let met: Vec<_> = stats.days.iter().map(|d| d.goal_met).collect();
+        assert_eq!(met, [true, true, false, true, true, true, false]);
+        assert_eq!(stats.longest_streak_days, 3);
+        assert_eq!(stats.weeks.len(), 1);
+        assert_eq!(stats.weeks[0].completed_sessions, 6);
+    }
+
+    #[test]
+    fn overlong_range_is_rejected() {
+        let range = Range {
+            from_ms: Some(0),
+            to_ms: i64::MAX,
+            offset_ms: 0,
+        };
+        assert!(matches!(
+            compute(&[], &range, 100),
+            Err(StatsError::RangeTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn bad_timestamps_are_rejected() {
+        assert!(matches!(
+            focus_stats(Some(10), Some(5), 0, 100, 0),
+            Err(StatsError::InvalidRange(10, 5))
+        ));
+        assert!(matches!(
+            focus_stats(None, Some(u64::MAX), 0, 100, 0),
+            Err(StatsError::InvalidTimestamp(u64::MAX))
+        ));
+    }
+}
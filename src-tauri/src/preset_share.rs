@@ -0,0 +1,384 @@
+use crate::config::{self, RainColor};
+use crate::presets::{self, Preset, PresetConfig, PresetError};
+use crate::schema::FieldError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Share strings look like `raindesk1.<base64url payload>`: the prefix,
+/// the format version, a dot and the encoded preset
+const SHARE_PREFIX: &str = "raindesk";
+
+/// Current share string format version
+const SHARE_VERSION: u32 = 1;
+
+/// Current `.raindesk-preset.toml` format version
+const FILE_VERSION: u32 = 1;
+
+/// Required ending of exported preset files
+const FILE_EXTENSION: &str = ".raindesk-preset.toml";
+
+/// Highest " (N)" suffix tried when an imported name is taken
+const MAX_NAME_SUFFIX: u32 = 99;
+
+/// Longest description accepted from a share string or file, in characters
+const MAX_DESCRIPTION_LEN: usize = 1000;
+
+#[derive(Error, Debug)]
+pub enum ShareError {
+    #[error(transparent)]
+    Preset(#[from] PresetError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("TOML serialization error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error("TOML deserialization error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
+    #[error("Not a RainDesk preset share string")]
+    NotAShareString,
+    #[error("Share string is damaged or incomplete")]
+    Corrupt,
+    #[error("Preset format version {0} is not supported (this release reads version {1})")]
+    UnsupportedVersion(u32, u32),
+    #[error("Preset files must end in {FILE_EXTENSION}: {0}")]
+    InvalidFileName(PathBuf),
+    #[error("File already exists: {0}")]
+    FileExists(PathBuf),
+    #[error("No presets found in file")]
+    Empty,
+    #[error("Preset name is {0} characters long; at most {max} are allowed", max = presets::MAX_NAME_LEN)]
+    NameTooLong(usize),
+    #[error(
+        "Preset description is {0} characters long; at most {MAX_DESCRIPTION_LEN} are allowed"
+    )]
+    DescriptionTooLong(usize),
+}
+
+/// A `.raindesk-preset.toml` file: one preset, or a pack of several
+#[derive(Serialize, Deserialize)]
+struct PresetFile {
+    version: u32,
+    presets: Vec<Preset>,
+}
+
+/// A preset added by an import
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedPreset {
+    /// Name it was saved under
+    pub name: String,
+    /// Name in the source, if it was already taken
+    pub renamed_from: Option<String>,
+    /// Out-of-range fields that were clamped
+    pub clamped: Vec<FieldError>,
+}
+
+// ============================================================================
+// Share strings
+// ============================================================================
+
+/// Encode a preset as a compact, URL-safe share string
+pub fn to_share_string(preset: &Preset) -> String {
+    format!(
+        "{}{}.{}",
+        SHARE_PREFIX,
+        SHARE_VERSION,
+        URL_SAFE_NO_PAD.encode(encode(preset))
+    )
+}
+
+/// Decode a share string; surrounding whitespace is ignored
+pub fn from_share_string(share: &str) -> Result<Preset, ShareError> {
+    let rest = share
+        .trim()
+        .strip_prefix(SHARE_PREFIX)
+        .ok_or(ShareError::NotAShareString)?;
+    let (version, payload) = rest.split_once('.').ok_or(ShareError::NotAShareString)?;
+    let version: u32 = version.parse().map_err(|_| ShareError::NotAShareString)?;
+    if version != SHARE_VERSION {
+        return Err(ShareError::UnsupportedVersion(version, SHARE_VERSION));
+    }
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| ShareError::Corrupt)?;
+    decode(&bytes)
+}
+
+/// Version 1 payload: a flags byte (bit 0: splashes), the float fields as
+/// little-endian f32, the RGBA color, then the name and description as
+/// length-prefixed UTF-8
+fn encode(preset: &Preset) -> Vec<u8> {
+    let c = &preset.config;
+    let mut bytes = vec![u8::from(c.splash_enabled)];
+    for value in [
+        c.intensity,
+        c.speed,
+        c.angle,
+        c.drop_length,
+        c.drop_width,
+        c.opacity,
+        c.splash_intensity,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&[c.color.r, c.color.g, c.color.b, c.color.a]);
+    for text in [&preset.name, &preset.description] {
+        bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(text.as_bytes());
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Result<Preset, ShareError> {
+    let mut reader = Reader { bytes };
+    let flags = reader.take::<1>()?[0];
+    let mut floats = [0.0; 7];
+    for value in &mut floats {
+        *value = f32::from_le_bytes(reader.take()?);
+    }
+    let [r, g, b, a] = reader.take()?;
+    let name = reader.string()?;
+    let description = reader.string()?;
+    if !reader.bytes.is_empty() {
+        return Err(ShareError::Corrupt);
+    }
+    check_lengths(&name, &description)?;
+    let [intensity, speed, angle, drop_length, drop_width, opacity, splash_intensity] = floats;
+    Ok(Preset {
+        name,
+        description,
+        config: PresetConfig {
+            intensity,
+            speed,
+            angle,
+            drop_length,
+            drop_width,
+            color: RainColor { r, g, b, a },
+            opacity,
+            splash_enabled: flags & 1 != 0,
+            splash_intensity,
+        },
+    })
+}
+
+/// Reject a name or description longer than a preset can have; the name is
+/// checked in full when the preset is saved
+fn check_lengths(name: &str, description: &str) -> Result<(), ShareError> {
+    let name_len = name.trim().chars().count();
+    if name_len > presets::MAX_NAME_LEN {
+        return Err(ShareError::NameTooLong(name_len));
+    }
+    let description_len = description.chars().count();
+    if description_len > MAX_DESCRIPTION_LEN {
+        return Err(ShareError::DescriptionTooLong(description_len));
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ShareError> {
+        let (head, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(ShareError::Corrupt)?;
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn string(&mut self) -> Result<String, ShareError> {
+        let len = u32::from_le_bytes(self.take()?) as usize;
+        if len > self.bytes.len() {
+            return Err(ShareError::Corrupt);
+        }
+        let (text, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        String::from_utf8(text.to_vec()).map_err(|_| ShareError::Corrupt)
+    }
+}
+
+// ============================================================================
+// Preset files and packs
+// ============================================================================
+
+/// Write presets to a `.raindesk-preset.toml` file. An existing file is
+/// only replaced when `overwrite` is set.
+pub fn write_file(presets: Vec<Preset>, path: &Path, overwrite: bool) -> Result<(), ShareError> {
+    let is_preset_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.len() > FILE_EXTENSION.len() && name.ends_with(FILE_EXTENSION));
+    if !is_preset_file {
+        return Err(ShareError::InvalidFileName(path.to_path_buf()));
+    }
+    let file = PresetFile {
+        version: FILE_VERSION,
+        presets,
+    };
+    let contents = toml::to_string_pretty(&file)?;
+    if overwrite {
+        config::write_atomically(path, &contents)?;
+        return Ok(());
+    }
+    // Created exclusively, so a file appearing in the meantime isn't clobbered
+    let mut out = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(out) => out,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(ShareError::FileExists(path.to_path_buf()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    out.write_all(contents.as_bytes())?;
+    out.sync_all()?;
+    Ok(())
+}
+
+/// Read the presets in a file: a `.raindesk-preset.toml` file or pack, or
+/// a single user preset file copied out of the presets directory
+pub fn read_file(path: &Path) -> Result<Vec<Preset>, ShareError> {
+    let table: toml::Table = fs::read_to_string(path)?.parse()?;
+    let presets = if table.contains_key("presets") {
+        let file: PresetFile = table.try_into()?;
+        if file.version != FILE_VERSION {
+            return Err(ShareError::UnsupportedVersion(file.version, FILE_VERSION));
+        }
+        file.presets
+    } else {
+        vec![table.try_into()?]
+    };
+    if presets.is_empty() {
+        return Err(ShareError::Empty);
+    }
+    for preset in &presets {
+        check_lengths(&preset.name, &preset.description)?;
+    }
+    Ok(presets)
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Save presets from a share string or file as user presets. Out-of-range
+/// values are clamped, and a name that is already taken gets a " (2)"
+/// style suffix. Nothing is saved if any name is invalid.
+pub fn import(incoming: Vec<Preset>) -> Result<Vec<ImportedPreset>, ShareError> {
+    for preset in &incoming {
+        presets::validate_name(&preset.name)?;
+    }
+    let mut imported = Vec::new();
+    for mut preset in incoming {
+        let clamped = preset.config.clamp();
+        let original = presets::validate_name(&preset.name)?;
+        let name = save_unique(&original, &preset.description, preset.config)?;
+        imported.push(ImportedPreset {
+            renamed_from: (name != original).then_some(original),
+            name,
+            clamped,
+        });
+    }
+    Ok(imported)
+}
+
+fn save_unique(
+    name: &str,
+    description: &str,
+    config: PresetConfig,
+) -> Result<String, ShareError> {
+    let candidates = std::iter::once(name.to_string()).chain((2..=MAX_NAME_SUFFIX).map(|n| {
+        // Shorten the name so it stays within the limit with the suffix
+        let suffix = format!(" ({})", n);
+        let base: String = name
+            .chars()
+            .take(presets::MAX_NAME_LEN - suffix.len())
+            .collect();
+        format!("{}{}", base.trim_end(), suffix)
+    }));
+    for candidate in candidates {
+        match presets::create(&candidate, description, config.clone()) {
+            Err(PresetError::AlreadyExists(_)) => continue,
+            result => return Ok(result?),
+        }
+    }
+    Err(PresetError::AlreadyExists(name.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(description: &str) -> Preset {
+        Preset {
+            name: "Night Drive".to_string(),
+            description: description.to_string(),
+            config: PresetConfig {
+                intensity: 0.35,
+                angle: -12.5,
+                splash_enabled: false,
+                ..PresetConfig::default()
+            },
+        }
+    }
+
+    #[test]
+    fn share_string_round_trip() {
+        let preset = preset("Slow rain under street lights ☔");
+        let share = to_share_string(&preset);
+        assert!(share.starts_with("raindesk1."));
+        assert_eq!(
+            from_share_string(&format!("  {}\n", share)).unwrap(),
+            preset
+        );
+    }
+
+    #[test]
+    fn truncated_or_garbled_share_strings_are_rejected() {
+        let share = to_share_string(&preset("A description"));
+        let truncated = &share[..share.len() - 3];
+        assert!(matches!(
+            from_share_string(truncated),
+            Err(ShareError::Corrupt)
+        ));
+        // Not base64
+        let garbled = share.replacen('.', ".!", 1);
+        assert!(matches!(
+            from_share_string(&garbled),
+            Err(ShareError::Corrupt)
+        ));
+        // Valid base64 of the wrong bytes
+        let garbled = format!("raindesk1.{}", URL_SAFE_NO_PAD.encode([1u8; 40]));
+        assert!(matches!(
+            from_share_string(&garbled),
+            Err(ShareError::Corrupt)
+        ));
+        assert!(matches!(
+            from_share_string("raindesk.abc"),
+            Err(ShareError::NotAShareString)
+        ));
+        assert!(matches!(
+            from_share_string("raindesk2.abc"),
+            Err(ShareError::UnsupportedVersion(2, 1))
+        ));
+    }
+
+    #[test]
+    fn overlong_names_and_descriptions_are_rejected() {
+        let long = preset(&"x".repeat(MAX_DESCRIPTION_LEN + 1));
+        assert!(matches!(
+            from_share_string(&to_share_string(&long)),
+            Err(ShareError::DescriptionTooLong(_))
+        ));
+        let mut long = preset("");
+        long.name = "n".repeat(presets::MAX_NAME_LEN + 1);
+        assert!(matches!(
+            from_share_string(&to_share_string(&long)),
+            Err(ShareError::NameTooLong(_))
+        ));
+    }
+}
@@ -1,16 +1,47 @@
-use crate::config::RainColor;
+use crate::config::{self, ConfigError, RainColor, RainConfig};
+use crate::schema::FieldError;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Longest allowed preset name
+pub const MAX_NAME_LEN: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum PresetError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("TOML serialization error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error("TOML deserialization error: {0}")]
+    TomlDeserialize(#[from] toml::de::Error),
+    #[error("Invalid preset name \"{0}\": use up to 64 characters, including a letter or digit")]
+    InvalidName(String),
+    #[error("A preset named \"{0}\" already exists")]
+    AlreadyExists(String),
+    #[error("Preset \"{0}\" not found")]
+    NotFound(String),
+    #[error("\"{0}\" is a built-in preset and can't be changed; clone it instead")]
+    ReadOnly(String),
+}
 
 /// A rain preset with a name and configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Preset {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub config: PresetConfig,
 }
 
 /// Subset of RainConfig used in presets (excludes enabled state)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PresetConfig {
     pub intensity: f32,
     pub speed: f32,
@@ -23,6 +54,62 @@ pub struct PresetConfig {
     pub splash_intensity: f32,
 }
 
+impl Default for PresetConfig {
+    fn default() -> Self {
+        Self::from(&RainConfig::default())
+    }
+}
+
+impl From<&RainConfig> for PresetConfig {
+    fn from(config: &RainConfig) -> Self {
+        Self {
+            intensity: config.intensity,
+            speed: config.speed,
+            angle: config.angle,
+            drop_length: config.drop_length,
+            drop_width: config.drop_width,
+            color: config.color.clone(),
+            opacity: config.opacity,
+            splash_enabled: config.splash_enabled,
+            splash_intensity: config.splash_intensity,
+        }
+    }
+}
+
+impl PresetConfig {
+    /// Copy the preset's look onto `config`, leaving everything else alone
+    pub fn apply_to(&self, config: &mut RainConfig) {
+        config.intensity = self.intensity;
+        config.speed = self.speed;
+        config.angle = self.angle;
+        config.drop_length = self.drop_length;
+        config.drop_width = self.drop_width;
+        config.color = self.color.clone();
+        config.opacity = self.opacity;
+        config.splash_enabled = self.splash_enabled;
+        config.splash_intensity = self.splash_intensity;
+    }
+
+    /// Clamp all values to valid ranges, returning the fields that were changed
+    pub fn clamp(&mut self) -> Vec<FieldError> {
+        let mut config = RainConfig::default();
+        self.apply_to(&mut config);
+        let clamped = config.clamp();
+        *self = Self::from(&config);
+        clamped
+    }
+}
+
+/// A preset as listed in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetEntry {
+    #[serde(flatten)]
+    pub preset: Preset,
+    /// Built-in presets are read-only
+    pub builtin: bool,
+    pub favorite: bool,
+}
+
 /// Get built-in presets
 pub fn get_builtin_presets() -> Vec<Preset> {
     vec![
@@ -188,3 +275,261 @@ pub fn get_builtin_presets() -> Vec<Preset> {
         },
     ]
 }
+
+// ============================================================================
+// User presets
+//
+// Built-in and user presets share one namespace. Names are compared
+// case-insensitively, ignoring punctuation and spacing ("Steady Rain" and
+// "steady-rain" are the same name), and a new user preset can't take a name
+// that is already in use. A user preset that still collides with a built-in
+// (added by hand, or a later release ships a built-in of that name) wins and
+// hides the built-in.
+// ============================================================================
+
+/// Directory holding one `<key>.toml` file per user preset
+pub fn presets_dir() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("presets"))
+}
+
+/// File listing favorite presets, built-in or user
+fn favorites_path() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("favorite_presets.toml"))
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Favorites {
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+/// Namespace key of a preset name, also used as its file name: lowercase
+/// letters and digits with single dashes in between
+pub fn key(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Check a preset name, returning it trimmed
+pub fn validate_name(name: &str) -> Result<String, PresetError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN || key(name).is_empty() {
+        return Err(PresetError::InvalidName(name.to_string()));
+    }
+    Ok(name.to_string())
+}
+
+fn preset_path(name: &str) -> Result<PathBuf, ConfigError> {
+    Ok(presets_dir()?.join(format!("{}.toml", key(name))))
+}
+
+fn write_preset(preset: &Preset) -> Result<(), PresetError> {
+    config::write_atomically(&preset_path(&preset.name)?, &toml::to_string_pretty(preset)?)?;
+    Ok(())
+}
+
+fn read_preset(path: &Path) -> Result<Preset, PresetError> {
+    let mut preset: Preset = toml::from_str(&fs::read_to_string(path)?)?;
+    let clamped = preset.config.clamp();
+    if !clamped.is_empty() {
+        eprintln!(
+            "[raindesk] Clamped out-of-range settings in preset {}: {}",
+            path.display(),
+            crate::schema::describe(&clamped)
+        );
+    }
+    Ok(preset)
+}
+
+/// User presets, sorted by name. Files that don't load are skipped.
+pub fn user_presets() -> Result<Vec<Preset>, PresetError> {
+    let mut presets = Vec::new();
+    let entries = match fs::read_dir(presets_dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(presets),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        match read_preset(&path) {
+            // Only a file at the name's own path counts, so lookups by name find it
+            Ok(preset) if preset_path(&preset.name)? == path => presets.push(preset),
+            Ok(_) => eprintln!(
+                "[raindesk] Skipping preset {}: file name doesn't match the preset name",
+                path.display()
+            ),
+            Err(e) => eprintln!("[raindesk] Skipping preset {}: {}", path.display(), e),
+        }
+    }
+    presets.sort_by_key(|p| p.name.to_lowercase());
+    Ok(presets)
+}
+
+fn find_user(name: &str) -> Result<Option<Preset>, PresetError> {
+    let path = preset_path(name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(read_preset(&path)?))
+}
+
+fn find_builtin(name: &str) -> Option<Preset> {
+    let key = key(name);
+    get_builtin_presets()
+        .into_iter()
+        .find(|p| self::key(&p.name) == key)
+}
+
+/// Look a preset up by name, user presets first
+pub fn find(name: &str) -> Result<Preset, PresetError> {
+    match find_user(name)? {
+        Some(preset) => Ok(preset),
+        None => find_builtin(name).ok_or_else(|| PresetError::NotFound(name.to_string())),
+    }
+}
+
+/// Fail unless `name` is a valid name no preset uses
+fn ensure_available(name: &str) -> Result<String, PresetError> {
+    let name = validate_name(name)?;
+    if find_builtin(&name).is_some() || preset_path(&name)?.exists() {
+        return Err(PresetError::AlreadyExists(name));
+    }
+    Ok(name)
+}
+
+/// Fail unless `name` is an existing user preset, which is returned
+fn ensure_user(name: &str) -> Result<Preset, PresetError> {
+    match find_user(name)? {
+        Some(preset) => Ok(preset),
+        None if find_builtin(name).is_some() => Err(PresetError::ReadOnly(name.to_string())),
+        None => Err(PresetError::NotFound(name.to_string())),
+    }
+}
+
+fn load_favorites() -> Vec<String> {
+    favorites_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| toml::from_str::<Favorites>(&contents).ok())
+        .map(|favorites| favorites.names)
+        .unwrap_or_default()
+}
+
+fn store_favorites(names: Vec<String>) -> Result<(), PresetError> {
+    let contents = toml::to_string_pretty(&Favorites { names })?;
+    config::write_atomically(&favorites_path()?, &contents)?;
+    Ok(())
+}
+
+/// Replace `old` with `new` in the favorites, or drop it if `new` is `None`
+fn update_favorite(old: &str, new: Option<&str>) -> Result<(), PresetError> {
+    let mut names = load_favorites();
+    let Some(index) = names.iter().position(|n| key(n) == key(old)) else {
+        return Ok(());
+    };
+    match new {
+        Some(new) => names[index] = new.to_string(),
+        None => {
+            names.remove(index);
+        }
+    }
+    store_favorites(names)
+}
+
+/// Every preset: built-ins not hidden by a user preset, then user presets
+pub fn list() -> Result<Vec<PresetEntry>, PresetError> {
+    let user = user_presets()?;
+    let user_keys: Vec<String> = user.iter().map(|p| key(&p.name)).collect();
+    let favorites: Vec<String> = load_favorites().iter().map(|n| key(n)).collect();
+    let builtins = get_builtin_presets()
+        .into_iter()
+        .filter(|b| !user_keys.contains(&key(&b.name)))
+        .map(|preset| (preset, true));
+    let entries = builtins
+        .chain(user.into_iter().map(|preset| (preset, false)))
+        .map(|(preset, builtin)| PresetEntry {
+            favorite: favorites.contains(&key(&preset.name)),
+            preset,
+            builtin,
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Save a new user preset, returning its name as stored
+pub fn create(
+    name: &str,
+    description: &str,
+    config: PresetConfig,
+) -> Result<String, PresetError> {
+    let name = ensure_available(name)?;
+    write_preset(&Preset {
+        name: name.clone(),
+        description: description.trim().to_string(),
+        config,
+    })?;
+    Ok(name)
+}
+
+/// Copy any preset, built-in or user, into a new user preset
+pub fn clone(source: &str, name: &str) -> Result<String, PresetError> {
+    let source = find(source)?;
+    create(name, &source.description, source.config)
+}
+
+/// Replace a user preset's look, and its description if one is given
+pub fn update(
+    name: &str,
+    description: Option<&str>,
+    config: PresetConfig,
+) -> Result<(), PresetError> {
+    let mut preset = ensure_user(name)?;
+    if let Some(description) = description {
+        preset.description = description.trim().to_string();
+    }
+    preset.config = config;
+    write_preset(&preset)
+}
+
+/// Rename a user preset, returning its new name. Changing only the
+/// capitalization of a name is allowed.
+pub fn rename(old: &str, new: &str) -> Result<String, PresetError> {
+    let mut preset = ensure_user(old)?;
+    let new = if key(old) == key(new) {
+        validate_name(new)?
+    } else {
+        ensure_available(new)?
+    };
+    let old_path = preset_path(&preset.name)?;
+    let old_name = std::mem::replace(&mut preset.name, new.clone());
+    write_preset(&preset)?;
+    if preset_path(&new)? != old_path {
+        fs::remove_file(old_path)?;
+    }
+    update_favorite(&old_name, Some(&new))?;
+    Ok(new)
+}
+
+/// Delete a user preset
+pub fn delete(name: &str) -> Result<(), PresetError> {
+    let preset = ensure_user(name)?;
+    fs::remove_file(preset_path(&preset.name)?)?;
+    update_favorite(&preset.name, None)
+}
+
+/// Mark or unmark any preset as a favorite
+pub fn set_favorite(name: &str, favorite: bool) -> Result<(), PresetError> {
+    let preset = find(name)?;
+    let mut names = load_favorites();
+    names.retain(|n| key(n) != key(&preset.name));
+    if favorite {
+        names.push(preset.name);
+    }
+    store_favorites(names)
+}
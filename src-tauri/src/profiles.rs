@@ -0,0 +1,191 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::config::{self, ConfigError, RainConfig};
+
+/// Profile stored in `config.toml`. It always exists and can't be renamed
+/// or deleted.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Longest allowed profile name
+const MAX_NAME_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid profile name \"{0}\": use up to 32 letters, digits, '-' or '_'")]
+    InvalidName(String),
+    #[error("Profile \"{0}\" already exists")]
+    AlreadyExists(String),
+    #[error("Profile \"{0}\" not found")]
+    NotFound(String),
+    #[error("The default profile can't be renamed or deleted")]
+    DefaultProfile,
+}
+
+/// Profile names and which one is active, as shown in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    /// Default profile first, the rest sorted by name
+    pub profiles: Vec<String>,
+}
+
+/// Directory holding every profile except the default one
+pub fn profiles_dir() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("profiles"))
+}
+
+/// File remembering the active profile across restarts
+fn active_file() -> Result<PathBuf, ConfigError> {
+    Ok(RainConfig::config_dir()?.join("active_profile"))
+}
+
+/// Config file of a profile: `config.toml` for the default profile,
+/// `profiles/<name>.toml` for the others
+pub fn profile_path(name: &str) -> Result<PathBuf, ConfigError> {
+    if name == DEFAULT_PROFILE {
+        RainConfig::config_path()
+    } else {
+        Ok(profiles_dir()?.join(format!("{}.toml", name)))
+    }
+}
+
+/// Profile names double as file names, so only allow a safe subset
+pub fn validate_name(name: &str) -> Result<(), ProfileError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ProfileError::InvalidName(name.to_string()))
+    }
+}
+
+pub fn exists(name: &str) -> Result<bool, ProfileError> {
+    Ok(name == DEFAULT_PROFILE || profile_path(name)?.exists())
+}
+
+/// Fail unless `name` is an existing profile
+pub fn ensure_exists(name: &str) -> Result<(), ProfileError> {
+    validate_name(name)?;
+    if exists(name)? {
+        Ok(())
+    } else {
+        Err(ProfileError::NotFound(name.to_string()))
+    }
+}
+
+/// Fail unless `name` is a valid name not taken by another profile
+pub fn ensure_available(name: &str) -> Result<(), ProfileError> {
+    validate_name(name)?;
+    if exists(name)? {
+        Err(ProfileError::AlreadyExists(name.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Names of all profiles, default first, the rest sorted
+pub fn list() -> Result<Vec<String>, ProfileError> {
+    let mut names = Vec::new();
+    match fs::read_dir(profiles_dir()?) {
+        Ok(entries) => {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                    continue;
+                }
+                // Skips backups such as `night.invalid-<millis>.toml`
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                    if name != DEFAULT_PROFILE && validate_name(name).is_ok() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    Ok(names)
+}
+
+/// Profile that was active when the app last ran, or the default profile
+/// if none was recorded or it has since been removed
+pub fn load_active() -> String {
+    let name = active_file()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.trim().to_string());
+    match name {
+        Some(name) if ensure_exists(&name).is_ok() => name,
+        _ => DEFAULT_PROFILE.to_string(),
+    }
+}
+
+/// Remember the active profile for the next launch
+pub fn store_active(name: &str) -> Result<(), ProfileError> {
+    config::write_atomically(&active_file()?, name)?;
+    Ok(())
+}
+
+/// Write a new profile holding `config`
+pub fn create(name: &str, config: &RainConfig) -> Result<(), ProfileError> {
+    ensure_available(name)?;
+    config.save(&profile_path(name)?)?;
+    Ok(())
+}
+
+/// Rename a profile's file along with its numbered backups
+pub fn rename(old: &str, new: &str) -> Result<(), ProfileError> {
+    if old == DEFAULT_PROFILE {
+        return Err(ProfileError::DefaultProfile);
+    }
+    ensure_exists(old)?;
+    ensure_available(new)?;
+    let old_path = profile_path(old)?;
+    let new_path = profile_path(new)?;
+    fs::rename(&old_path, &new_path)?;
+    for (from, to) in config::backup_paths(&old_path)
+        .into_iter()
+        .zip(config::backup_paths(&new_path))
+    {
+        if from.exists() {
+            fs::rename(from, to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless `name` is a profile that can be deleted
+pub fn ensure_deletable(name: &str) -> Result<(), ProfileError> {
+    if name == DEFAULT_PROFILE {
+        return Err(ProfileError::DefaultProfile);
+    }
+    ensure_exists(name)
+}
+
+/// Delete a profile's file along with its numbered backups
+pub fn delete(name: &str) -> Result<(), ProfileError> {
+    ensure_deletable(name)?;
+    let path = profile_path(name)?;
+    fs::remove_file(&path)?;
+    for backup in config::backup_paths(&path) {
+        if backup.exists() {
+            fs::remove_file(backup)?;
+        }
+    }
+    Ok(())
+}
@@ -3,14 +3,25 @@ use std::ffi::c_void;
 use wayland_client::protocol::wl_display::WlDisplay;
 use wayland_client::Proxy;
 
-/// EGL state for rendering to a Wayland surface
+/// EGL display and context, shared by every output surface
 pub struct EglState {
     pub instance: egl::DynamicInstance<egl::EGL1_4>,
     pub display: egl::Display,
     pub context: egl::Context,
     pub egl_config: egl::Config,
-    pub surface: Option<egl::Surface>,
-    pub wl_egl_surface: Option<wayland_egl::WlEglSurface>,
+}
+
+/// EGL window surface backing a single Wayland surface
+pub struct EglSurface {
+    pub surface: egl::Surface,
+    pub wl_egl_surface: wayland_egl::WlEglSurface,
+}
+
+impl EglSurface {
+    /// Resize the EGL surface
+    pub fn resize(&self, width: i32, height: i32) {
+        self.wl_egl_surface.resize(width, height, 0, 0);
+    }
 }
 
 impl EglState {
@@ -76,22 +87,20 @@ impl EglState {
             display,
             context,
             egl_config,
-            surface: None,
-            wl_egl_surface: None,
         })
     }
 
     /// Create an EGL window surface for a Wayland surface
     pub fn create_surface(
-        &mut self,
+        &self,
         wl_surface: &wayland_client::protocol::wl_surface::WlSurface,
         width: i32,
         height: i32,
-    ) -> Result<(), String> {
+    ) -> Result<EglSurface, String> {
         let wl_egl_surface = wayland_egl::WlEglSurface::new(wl_surface.id(), width, height)
             .map_err(|e| format!("Failed to create WlEglSurface: {}", e))?;
 
-        let egl_surface = unsafe {
+        let surface = unsafe {
             self.instance.create_window_surface(
                 self.display,
                 self.egl_config,
@@ -101,35 +110,42 @@ impl EglState {
         }
         .map_err(|e| format!("eglCreateWindowSurface failed: {}", e))?;
 
-        self.surface = Some(egl_surface);
-        self.wl_egl_surface = Some(wl_egl_surface);
-
-        Ok(())
+        Ok(EglSurface {
+            surface,
+            wl_egl_surface,
+        })
     }
 
-    /// Resize the EGL surface
-    pub fn resize(&mut self, width: i32, height: i32) {
-        if let Some(ref wl_egl_surface) = self.wl_egl_surface {
-            wl_egl_surface.resize(width, height, 0, 0);
-        }
+    /// Destroy an EGL window surface. The `WlEglSurface` is released when dropped.
+    pub fn destroy_surface(&self, surface: EglSurface) {
+        let _ = self.instance.destroy_surface(self.display, surface.surface);
     }
 
-    /// Make this context current
-    pub fn make_current(&self) -> Result<(), String> {
+    /// Make this context current on the given surface
+    pub fn make_current(&self, surface: &EglSurface) -> Result<(), String> {
         self.instance
-            .make_current(self.display, self.surface, self.surface, Some(self.context))
+            .make_current(
+                self.display,
+                Some(surface.surface),
+                Some(surface.surface),
+                Some(self.context),
+            )
             .map_err(|e| format!("eglMakeCurrent failed: {}", e))
     }
 
-    /// Swap buffers
-    pub fn swap_buffers(&self) -> Result<(), String> {
-        if let Some(surface) = self.surface {
-            self.instance
-                .swap_buffers(self.display, surface)
-                .map_err(|e| format!("eglSwapBuffers failed: {}", e))
-        } else {
-            Err("No EGL surface".to_string())
-        }
+    /// Set the swap interval for the surface that is current.
+    /// 0 keeps `eglSwapBuffers` from blocking on the compositor.
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), String> {
+        self.instance
+            .swap_interval(self.display, interval)
+            .map_err(|e| format!("eglSwapInterval failed: {}", e))
+    }
+
+    /// Swap buffers of the given surface
+    pub fn swap_buffers(&self, surface: &EglSurface) -> Result<(), String> {
+        self.instance
+            .swap_buffers(self.display, surface.surface)
+            .map_err(|e| format!("eglSwapBuffers failed: {}", e))
     }
 
     /// Create a glow context from EGL
@@ -147,9 +163,7 @@ impl EglState {
 
 impl Drop for EglState {
     fn drop(&mut self) {
-        if let Some(surface) = self.surface.take() {
-            let _ = self.instance.destroy_surface(self.display, surface);
-        }
+        let _ = self.instance.make_current(self.display, None, None, None);
         let _ = self
             .instance
             .destroy_context(self.display, self.context);
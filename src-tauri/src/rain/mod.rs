@@ -2,3 +2,5 @@ pub mod egl;
 pub mod overlay;
 pub mod particles;
 pub mod renderer;
+pub mod stats;
+pub mod transition;
@@ -1,37 +1,52 @@
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use calloop::channel::{self, Channel};
+use calloop::timer::{TimeoutAction, Timer};
+use calloop::{EventLoop, LoopHandle, LoopSignal, RegistrationToken};
+use calloop_wayland_source::WaylandSource;
 use wayland_client::globals::{registry_queue_init, GlobalListContents};
-use wayland_client::protocol::{wl_compositor, wl_output, wl_region, wl_registry, wl_surface};
-use wayland_client::{delegate_noop, Connection, Dispatch, QueueHandle};
+use wayland_client::protocol::{
+    wl_callback, wl_compositor, wl_output, wl_region, wl_registry, wl_surface,
+};
+use wayland_client::{delegate_noop, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::wp::fractional_scale::v1::client::{
+    wp_fractional_scale_manager_v1, wp_fractional_scale_v1,
+};
+use wayland_protocols::wp::viewporter::client::{wp_viewport, wp_viewporter};
 use wayland_protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1, zwlr_layer_surface_v1,
 };
 
-use crate::config::RainConfig;
-use crate::rain::egl::EglState;
+use crate::config::{OverlayLayer, RainConfig, RainRegion};
+use crate::rain::egl::{EglState, EglSurface};
 use crate::rain::particles::ParticleSystem;
 use crate::rain::renderer::Renderer;
+use crate::rain::stats::{FrameSummary, FrameTimings, OutputStats, OverlayStats};
+use crate::rain::transition::Transition;
 use crate::state::OverlaySignal;
 
-/// Runs the rain overlay on the current thread (blocking).
-/// Call from a dedicated `std::thread::spawn`.
-pub fn run_overlay(config: Arc<Mutex<RainConfig>>, rx: mpsc::Receiver<OverlaySignal>) {
-    if let Err(e) = run_overlay_inner(config, rx) {
-        eprintln!("[raindesk overlay] Error: {}", e);
-    }
-}
+/// Largest simulation step, so drops don't jump after the surface was hidden
+const MAX_FRAME_DT: f32 = 0.1;
+
+/// How often a stats snapshot is sent to the app
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
 
-fn run_overlay_inner(
+/// Runs the rain overlay on the current thread (blocking) until shutdown is
+/// signalled or the channel closes. `on_running` is called once Wayland and
+/// EGL are up and the event loop starts; performance stats are sent on
+/// `stats_tx` while rain is drawn. Call from a dedicated thread.
+pub fn run_overlay(
     config: Arc<Mutex<RainConfig>>,
-    rx: mpsc::Receiver<OverlaySignal>,
+    rx: Channel<OverlaySignal>,
+    stats_tx: mpsc::Sender<OverlayStats>,
+    on_running: impl FnOnce(),
 ) -> Result<(), String> {
     // Connect to Wayland
     eprintln!("[raindesk overlay] Connecting to Wayland...");
     let conn = Connection::connect_to_env().map_err(|e| format!("Wayland connect: {}", e))?;
     eprintln!("[raindesk overlay] Connected to Wayland");
-    let (globals, mut event_queue) =
+    let (globals, event_queue) =
         registry_queue_init::<OverlayState>(&conn).map_err(|e| format!("Registry init: {}", e))?;
     let qh = event_queue.handle();
 
@@ -43,167 +58,688 @@ fn run_overlay_inner(
         .bind(&qh, 1..=4, ())
         .map_err(|e| format!("zwlr_layer_shell_v1: {}", e))?;
 
-    // Create surface
-    let wl_surface = compositor.create_surface(&qh, ());
-
-    // Create layer surface (Overlay layer, all edges anchored, fullscreen)
-    let layer_surface = layer_shell.get_layer_surface(
-        &wl_surface,
-        None, // default output
-        zwlr_layer_shell_v1::Layer::Overlay,
-        "raindesk".to_string(),
-        &qh,
-        (),
-    );
-
-    // Configure: anchor all edges (fullscreen), exclusive zone -1 (don't reserve space)
-    layer_surface.set_anchor(
-        zwlr_layer_surface_v1::Anchor::Top
-            | zwlr_layer_surface_v1::Anchor::Bottom
-            | zwlr_layer_surface_v1::Anchor::Left
-            | zwlr_layer_surface_v1::Anchor::Right,
-    );
-    layer_surface.set_exclusive_zone(-1);
-    layer_surface.set_keyboard_interactivity(
-        zwlr_layer_surface_v1::KeyboardInteractivity::None,
-    );
-
-    // Set empty input region (click-through)
-    let region: wl_region::WlRegion = compositor.create_region(&qh, ());
-    wl_surface.set_input_region(Some(&region));
-    region.destroy();
-
-    // Initial commit to get configure event
-    wl_surface.commit();
-
-    // State
-    let mut state = OverlayState {
-        configured: false,
-        width: 0,
-        height: 0,
-        closed: false,
+    // Fractional scaling needs both protocols; without them we fall back to
+    // integer wl_surface buffer scale
+    let fractional_scale_manager = globals.bind(&qh, 1..=1, ()).ok();
+    let viewporter = globals.bind(&qh, 1..=1, ()).ok();
+    let (fractional_scale_manager, viewporter) = match (fractional_scale_manager, viewporter) {
+        (Some(manager), Some(viewporter)) => (Some(manager), Some(viewporter)),
+        _ => (None, None),
     };
 
-    // Process events until we get a configure
-    while !state.configured && !state.closed {
-        event_queue
-            .blocking_dispatch(&mut state)
-            .map_err(|e| format!("Dispatch: {}", e))?;
-    }
-
-    if state.closed || state.width == 0 || state.height == 0 {
-        return Err("Layer surface closed or zero size".to_string());
-    }
-
-    let mut w = state.width as i32;
-    let mut h = state.height as i32;
-    eprintln!("[raindesk overlay] Configured: {}x{}", w, h);
-
     // Initialize EGL
     eprintln!("[raindesk overlay] Initializing EGL...");
     let wl_display = conn.display();
-    let mut egl = EglState::new(&wl_display)?;
-    egl.create_surface(&wl_surface, w, h)?;
-    egl.make_current()?;
+    let egl = EglState::new(&wl_display)?;
     eprintln!("[raindesk overlay] EGL initialized");
 
-    // Create GL context and renderer
-    let gl = egl.create_gl_context();
-    let mut renderer = Renderer::new(gl, w as f32, h as f32)?;
-    eprintln!("[raindesk overlay] Renderer ready");
-
-    // Create particle system
-    let initial_config = config.lock().unwrap().clone();
-    let mut particles = ParticleSystem::new(w as f32, h as f32, &initial_config);
-
-    // Frame loop
-    eprintln!("[raindesk overlay] Entering frame loop");
-    let mut last_frame = Instant::now();
-    let frame_target = std::time::Duration::from_micros(16_667); // ~60fps
-
-    loop {
-        // Check for shutdown/config signals (non-blocking)
-        loop {
-            match rx.try_recv() {
-                Ok(OverlaySignal::Shutdown) => return Ok(()),
-                Ok(OverlaySignal::ConfigChanged) => {
-                    let cfg = config.lock().unwrap().clone();
-                    particles.update_config(&cfg);
-                }
-                Err(mpsc::TryRecvError::Empty) => break,
-                Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+    let mut event_loop: EventLoop<'static, OverlayState> =
+        EventLoop::try_new().map_err(|e| format!("Event loop: {}", e))?;
+
+    // Bind every output present at startup; later ones arrive as registry events
+    let cfg = config.lock().unwrap().clone();
+    let mut state = OverlayState {
+        globals: OverlayGlobals {
+            compositor,
+            layer_shell,
+            fractional_scale_manager,
+            viewporter,
+        },
+        layer: cfg.layer,
+        region: cfg.region.clone(),
+        outputs: Vec::new(),
+        egl,
+        config,
+        cfg,
+        transition: None,
+        qh,
+        loop_handle: event_loop.handle(),
+        loop_signal: event_loop.get_signal(),
+        frame_timer: None,
+        stats_tx,
+        stats_window_start: Instant::now(),
+        gl_renderer: None,
+        error: None,
+    };
+    let registry = globals.registry();
+    let initial_outputs: Vec<(u32, u32)> = globals.contents().with_list(|list| {
+        list.iter()
+            .filter(|g| g.interface == wl_output::WlOutput::interface().name)
+            .map(|g| (g.name, g.version))
+            .collect()
+    });
+    for (name, version) in initial_outputs {
+        state.add_output(registry, name, version);
+    }
+
+    // Event sources: the Wayland socket, signals from the UI, and a frame
+    // timer that is armed only while the FPS cap holds a frame back
+    WaylandSource::new(conn.clone(), event_queue)
+        .insert(event_loop.handle())
+        .map_err(|e| format!("Wayland source: {}", e.error))?;
+    event_loop
+        .handle()
+        .insert_source(rx, |event, _, state| match event {
+            channel::Event::Msg(OverlaySignal::ConfigChanged { transition_ms }) => {
+                state.reload_config(transition_ms)
+            }
+            channel::Event::Msg(OverlaySignal::Shutdown) | channel::Event::Closed => {
+                state.loop_signal.stop();
+            }
+        })
+        .map_err(|e| format!("Signal source: {}", e.error))?;
+
+    // Sleep until a source fires, then draw whatever is ready
+    eprintln!("[raindesk overlay] Entering event loop");
+    on_running();
+    let result = event_loop
+        .run(None, &mut state, |state| {
+            if let Err(e) = state.frame() {
+                state.error = Some(e);
+                state.loop_signal.stop();
             }
+        })
+        .map_err(|e| format!("Event loop: {}", e));
+
+    // Release per-output resources while EGL is still alive
+    for output in state.outputs.drain(..) {
+        output.destroy(&state.egl);
+    }
+    let _ = conn.flush();
+    result?;
+    state.error.map_or(Ok(()), Err)
+}
+
+/// Overlay state, also the calloop event loop data
+struct OverlayState {
+    globals: OverlayGlobals,
+    /// Layer the output surfaces are currently placed on
+    layer: OverlayLayer,
+    /// Region the output surfaces currently cover
+    region: RainRegion,
+    outputs: Vec<OutputSurface>,
+    egl: EglState,
+    /// Shared config, re-read on `OverlaySignal::ConfigChanged`
+    config: Arc<Mutex<RainConfig>>,
+    /// Local copy of the config the overlay is currently using
+    cfg: RainConfig,
+    /// Running animation from the previous look to `cfg`
+    transition: Option<Transition>,
+    qh: QueueHandle<OverlayState>,
+    loop_handle: LoopHandle<'static, OverlayState>,
+    loop_signal: LoopSignal,
+    /// Armed frame timer and its deadline
+    frame_timer: Option<(RegistrationToken, Instant)>,
+    stats_tx: mpsc::Sender<OverlayStats>,
+    /// Start of the current stats reporting window
+    stats_window_start: Instant,
+    /// `GL_RENDERER` string, read once the first renderer exists
+    gl_renderer: Option<String>,
+    /// Error that stopped the event loop
+    error: Option<String>,
+}
+
+/// Globals needed to create a surface on a new output
+struct OverlayGlobals {
+    compositor: wl_compositor::WlCompositor,
+    layer_shell: zwlr_layer_shell_v1::ZwlrLayerShellV1,
+    /// Present only when `viewporter` is too
+    fractional_scale_manager: Option<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
+    viewporter: Option<wp_viewporter::WpViewporter>,
+}
+
+impl OverlayState {
+    /// Bind a `wl_output` global and create a layer surface on it
+    fn add_output(&mut self, registry: &wl_registry::WlRegistry, global_name: u32, version: u32) {
+        let output: wl_output::WlOutput =
+            registry.bind(global_name, version.min(4), &self.qh, global_name);
+        self.outputs.push(OutputSurface::new(
+            global_name,
+            output,
+            &self.globals,
+            self.layer,
+            &self.region,
+            &self.qh,
+        ));
+    }
+
+    /// Re-read the shared config and apply it to the surfaces, then animate
+    /// the particles towards it over `transition_ms` (or the configured
+    /// transition duration)
+    fn reload_config(&mut self, transition_ms: Option<u32>) {
+        let now = Instant::now();
+        // Start from what's on screen, which may be partway through a transition
+        let from = match self.transition.as_mut() {
+            Some(transition) => transition.step(now).clone(),
+            None => self.cfg.clone(),
+        };
+        self.cfg = self.config.lock().unwrap().clone();
+        if self.cfg.layer != self.layer {
+            self.set_layer(self.cfg.layer);
         }
+        if self.cfg.region != self.region {
+            self.set_region(self.cfg.region.clone());
+        }
+        let duration_ms = transition_ms.unwrap_or(self.cfg.transition.duration_ms);
+        self.transition = (duration_ms > 0).then(|| {
+            let duration = Duration::from_millis(duration_ms.into());
+            Transition::new(from, self.cfg.clone(), duration, now)
+        });
+        self.update_particles(now);
+    }
+
+    /// Give every output's particles the config to draw at `now`, and drop
+    /// the transition once it has finished
+    fn update_particles(&mut self, now: Instant) {
+        let cfg = match self.transition.as_mut() {
+            Some(transition) => transition.step(now),
+            None => &self.cfg,
+        };
+        for gfx in self.outputs.iter_mut().filter_map(|o| o.gfx.as_mut()) {
+            gfx.particles.update_config(cfg);
+        }
+        if self.transition.as_ref().is_some_and(|t| t.is_finished(now)) {
+            self.transition = None;
+        }
+    }
 
-        // Dispatch any pending Wayland events (non-blocking)
-        event_queue
-            .dispatch_pending(&mut state)
-            .map_err(|e| format!("Dispatch: {}", e))?;
+    /// Runs after every event loop dispatch: tear down closed surfaces, set up
+    /// newly configured ones, and draw outputs whose frame callback has fired
+    fn frame(&mut self) -> Result<(), String> {
+        // Tear down surfaces that were closed or whose output went away
+        let (closed, open): (Vec<_>, Vec<_>) = self.outputs.drain(..).partition(|o| o.closed);
+        self.outputs = open;
+        // Report right away when surfaces go away, the loop may go to sleep
+        let mut stats_changed = !closed.is_empty();
+        for output in closed {
+            eprintln!("[raindesk overlay] Removing surface on {}", output.label());
+            output.destroy(&self.egl);
+        }
 
-        // Flush the display to send any pending requests
-        let _ = conn.flush();
+        // Create GL resources for newly configured surfaces and handle resizes
+        for output in &mut self.outputs {
+            output.sync_gfx(&self.egl, &self.cfg)?;
+        }
+        if self.gl_renderer.is_none() {
+            if let Some(gfx) = self.outputs.iter().find_map(|o| o.gfx.as_ref()) {
+                self.gl_renderer = Some(gfx.renderer.gl_renderer());
+            }
+        }
 
-        // Read any events from the Wayland socket (non-blocking)
-        if let Some(guard) = conn.prepare_read() {
-            let _ = guard.read();
+        // Once disabled rain has faded out, unmap the surface so nothing is drawn
+        // or composited; with no frame callbacks pending the loop sleeps until a signal
+        for output in &mut self.outputs {
+            if output.gfx.as_ref().is_some_and(|gfx| gfx.particles.is_idle()) {
+                eprintln!("[raindesk overlay] Rain idle on {}", output.label());
+                output.unmap(&self.egl);
+                stats_changed = true;
+            }
         }
-        event_queue
-            .dispatch_pending(&mut state)
-            .map_err(|e| format!("Dispatch: {}", e))?;
 
-        if state.closed {
-            return Ok(());
+        // Render every output whose frame callback has fired, within the FPS cap
+        let max_fps = self.cfg.max_fps;
+        let min_interval = (max_fps > 0).then(|| Duration::from_secs(1) / max_fps);
+        let now = Instant::now();
+        if self.transition.is_some() {
+            self.update_particles(now);
+        }
+        let mut held_until: Option<Instant> = None;
+        for output in &mut self.outputs {
+            let Some(gfx) = output.gfx.as_mut() else {
+                continue;
+            };
+            if gfx.frame_pending {
+                continue;
+            }
+            if let (Some(min), Some(last)) = (min_interval, gfx.last_render) {
+                let due = last + min;
+                if now < due {
+                    held_until = Some(held_until.map_or(due, |t| t.min(due)));
+                    continue;
+                }
+            }
+            gfx.render_frame(&self.egl, &output.wl_surface, output.global_name, &self.qh)?;
         }
 
-        // Handle resize
-        if state.width as i32 != w || state.height as i32 != h {
-            w = state.width as i32;
-            h = state.height as i32;
-            egl.resize(w, h);
-            renderer.resize(w as f32, h as f32);
-            particles.resize(w as f32, h as f32);
+        if let Some(deadline) = held_until {
+            self.arm_frame_timer(deadline);
+        }
+        if stats_changed || now.duration_since(self.stats_window_start) >= STATS_INTERVAL {
+            self.publish_stats();
         }
+        Ok(())
+    }
 
-        // Calculate delta time
+    /// Send a stats snapshot covering every output and start a new window
+    fn publish_stats(&mut self) {
         let now = Instant::now();
-        let dt = now.duration_since(last_frame).as_secs_f32();
-        last_frame = now;
+        let window = now.duration_since(self.stats_window_start);
+        self.stats_window_start = now;
+        let outputs = self
+            .outputs
+            .iter_mut()
+            .map(|output| {
+                let (summary, drops, splashes) = match output.gfx.as_mut() {
+                    Some(gfx) => (
+                        gfx.timings.take(window),
+                        gfx.particles.drops.len(),
+                        gfx.particles.splashes.len(),
+                    ),
+                    None => (FrameSummary::default(), 0, 0),
+                };
+                OutputStats {
+                    name: output.label(),
+                    width: output.width,
+                    height: output.height,
+                    scale: output.scale(),
+                    active: output.gfx.is_some(),
+                    fps: summary.fps,
+                    frame_time_p50_ms: summary.p50_ms,
+                    frame_time_p95_ms: summary.p95_ms,
+                    frame_time_p99_ms: summary.p99_ms,
+                    frame_time_max_ms: summary.max_ms,
+                    drops,
+                    splashes,
+                }
+            })
+            .collect();
+        // The receiving side is gone only while the app shuts down
+        let _ = self.stats_tx.send(OverlayStats {
+            gl_renderer: self.gl_renderer.clone(),
+            outputs,
+        });
+    }
 
-        // Update particles
-        particles.update(dt);
+    /// Wake the loop at `deadline` so frames held back by the FPS cap get drawn
+    fn arm_frame_timer(&mut self, deadline: Instant) {
+        if let Some((token, armed)) = self.frame_timer {
+            if armed <= deadline {
+                return;
+            }
+            self.loop_handle.remove(token);
+        }
+        self.frame_timer = self
+            .loop_handle
+            .insert_source(Timer::from_deadline(deadline), |_, _, state| {
+                state.frame_timer = None;
+                TimeoutAction::Drop
+            })
+            .ok()
+            .map(|token| (token, deadline));
+    }
 
-        // Render
-        egl.make_current()?;
-        renderer.render(&particles);
-        egl.swap_buffers()?;
-        let _ = conn.flush();
+    /// Re-anchor every surface; the compositor answers with a configure
+    /// carrying the new size, which resizes the particle systems
+    fn set_region(&mut self, region: RainRegion) {
+        for output in &self.outputs {
+            output.apply_region(&region);
+            output.wl_surface.commit();
+        }
+        self.region = region;
+    }
 
-        // Frame pacing — sleep for remainder of frame budget
-        let elapsed = now.elapsed();
-        if elapsed < frame_target {
-            std::thread::sleep(frame_target - elapsed);
+    /// Move every surface to a new layer. `set_layer` needs layer-shell v2;
+    /// on v1 the surfaces are destroyed and recreated on the same outputs.
+    fn set_layer(&mut self, layer: OverlayLayer) {
+        eprintln!("[raindesk overlay] Switching to {:?} layer", layer);
+        self.layer = layer;
+        if self.globals.layer_shell.version() >= 2 {
+            // Applied on the next commit, i.e. the next eglSwapBuffers
+            for output in &self.outputs {
+                output.layer_surface.set_layer(shell_layer(layer));
+            }
+            return;
+        }
+        for old in std::mem::take(&mut self.outputs) {
+            let global_name = old.global_name;
+            let name = old.name.clone();
+            let output_scale = old.output_scale;
+            let output = old.into_output(&self.egl);
+            let mut surface = OutputSurface::new(
+                global_name,
+                output,
+                &self.globals,
+                layer,
+                &self.region,
+                &self.qh,
+            );
+            surface.name = name;
+            surface.output_scale = output_scale;
+            self.outputs.push(surface);
         }
     }
+
+    fn output_mut(&mut self, global_name: u32) -> Option<&mut OutputSurface> {
+        self.outputs
+            .iter_mut()
+            .find(|o| o.global_name == global_name)
+    }
 }
 
-/// Minimal state for the overlay Wayland client
-struct OverlayState {
+/// Layer surface covering a single `wl_output`
+struct OutputSurface {
+    /// Registry name of the `wl_output` global
+    global_name: u32,
+    output: wl_output::WlOutput,
+    /// Connector name reported by the compositor (e.g. "DP-1")
+    name: Option<String>,
+    wl_surface: wl_surface::WlSurface,
+    layer_surface: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    fractional_scale: Option<wp_fractional_scale_v1::WpFractionalScaleV1>,
+    viewport: Option<wp_viewport::WpViewport>,
     configured: bool,
+    /// Logical size from the layer surface configure event
     width: u32,
     height: u32,
+    /// Integer scale advertised by `wl_output`
+    output_scale: i32,
+    /// Preferred scale from `wp_fractional_scale_v1`, in 120ths
+    preferred_scale: Option<u32>,
     closed: bool,
+    gfx: Option<OutputGfx>,
+}
+
+/// GL resources for an output, created once its layer surface has a size
+struct OutputGfx {
+    egl_surface: EglSurface,
+    renderer: Renderer,
+    particles: ParticleSystem,
+    width: i32,
+    height: i32,
+    scale: f64,
+    /// A `wl_surface.frame` callback was requested and hasn't fired yet
+    frame_pending: bool,
+    /// Timestamp (ms) of the latest frame callback
+    frame_time: Option<u32>,
+    /// Timestamp (ms) of the callback the last rendered frame was based on
+    last_frame_time: Option<u32>,
+    last_render: Option<Instant>,
+    timings: FrameTimings,
+}
+
+impl OutputGfx {
+    /// Advance the particles and draw one frame. A frame callback is
+    /// requested before the swap so it's part of the same commit.
+    fn render_frame(
+        &mut self,
+        egl: &EglState,
+        wl_surface: &wl_surface::WlSurface,
+        global_name: u32,
+        qh: &QueueHandle<OverlayState>,
+    ) -> Result<(), String> {
+        let now = Instant::now();
+        // dt comes from the compositor's presentation timestamps when we have two
+        let dt = match (self.last_frame_time, self.frame_time) {
+            (Some(prev), Some(cur)) => cur.wrapping_sub(prev) as f32 / 1000.0,
+            _ => self
+                .last_render
+                .map_or(0.0, |last| now.duration_since(last).as_secs_f32()),
+        };
+        self.last_frame_time = self.frame_time;
+        self.last_render = Some(now);
+
+        self.particles.update(dt.min(MAX_FRAME_DT));
+        egl.make_current(&self.egl_surface)?;
+        self.renderer.render(&self.particles);
+        wl_surface.frame(qh, global_name);
+        self.frame_pending = true;
+        egl.swap_buffers(&self.egl_surface)?;
+        self.timings.record(now.elapsed());
+        Ok(())
+    }
+
+    /// Delete GL objects (with the context current) and the EGL surface
+    fn destroy(self, egl: &EglState) {
+        let _ = egl.make_current(&self.egl_surface);
+        drop(self.renderer);
+        egl.destroy_surface(self.egl_surface);
+    }
+}
+
+impl OutputSurface {
+    fn new(
+        global_name: u32,
+        output: wl_output::WlOutput,
+        globals: &OverlayGlobals,
+        layer: OverlayLayer,
+        region: &RainRegion,
+        qh: &QueueHandle<OverlayState>,
+    ) -> Self {
+        // Create surface
+        let wl_surface = globals.compositor.create_surface(qh, ());
+
+        // Create layer surface on the configured layer
+        let layer_surface = globals.layer_shell.get_layer_surface(
+            &wl_surface,
+            Some(&output),
+            shell_layer(layer),
+            "raindesk".to_string(),
+            qh,
+            global_name,
+        );
+
+        // Exclusive zone -1: don't reserve space or get pushed by other surfaces
+        layer_surface.set_exclusive_zone(-1);
+        layer_surface.set_keyboard_interactivity(
+            zwlr_layer_surface_v1::KeyboardInteractivity::None,
+        );
+
+        // Set empty input region (click-through)
+        let input_region: wl_region::WlRegion = globals.compositor.create_region(qh, ());
+        wl_surface.set_input_region(Some(&input_region));
+        input_region.destroy();
+
+        // Render at native density: the viewport maps the scaled buffer back to logical size
+        let fractional_scale = globals
+            .fractional_scale_manager
+            .as_ref()
+            .map(|manager| manager.get_fractional_scale(&wl_surface, qh, global_name));
+        let viewport = globals
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(&wl_surface, qh, ()));
+
+        let surface = Self {
+            global_name,
+            output,
+            name: None,
+            wl_surface,
+            layer_surface,
+            fractional_scale,
+            viewport,
+            configured: false,
+            width: 0,
+            height: 0,
+            output_scale: 1,
+            preferred_scale: None,
+            closed: false,
+            gfx: None,
+        };
+        surface.apply_region(region);
+
+        // Initial commit to get configure event
+        surface.wl_surface.commit();
+        surface
+    }
+
+    /// Set anchors, margins and size from the rain region (applied on commit)
+    fn apply_region(&self, region: &RainRegion) {
+        let mut anchor = zwlr_layer_surface_v1::Anchor::empty();
+        if region.anchor_top {
+            anchor |= zwlr_layer_surface_v1::Anchor::Top;
+        }
+        if region.anchor_bottom {
+            anchor |= zwlr_layer_surface_v1::Anchor::Bottom;
+        }
+        if region.anchor_left {
+            anchor |= zwlr_layer_surface_v1::Anchor::Left;
+        }
+        if region.anchor_right {
+            anchor |= zwlr_layer_surface_v1::Anchor::Right;
+        }
+        self.layer_surface.set_anchor(anchor);
+        self.layer_surface.set_margin(
+            region.margin_top,
+            region.margin_right,
+            region.margin_bottom,
+            region.margin_left,
+        );
+        // 0 lets the compositor stretch the surface between opposite anchors
+        self.layer_surface
+            .set_size(region.width.unwrap_or(0), region.height.unwrap_or(0));
+    }
+
+    /// Buffer-to-logical scale: fractional when the viewporter is available,
+    /// otherwise the output's integer scale
+    fn scale(&self) -> f64 {
+        match (self.viewport.as_ref(), self.preferred_scale) {
+            (Some(_), Some(preferred)) => preferred as f64 / 120.0,
+            _ => self.output_scale.max(1) as f64,
+        }
+    }
+
+    /// Tell the compositor how the buffer maps onto the logical surface.
+    /// Takes effect on the next commit (the next `eglSwapBuffers`).
+    fn apply_scale(&self, width: i32, height: i32, scale: f64) {
+        match self.viewport.as_ref() {
+            Some(viewport) => viewport.set_destination(width, height),
+            None => self.wl_surface.set_buffer_scale(scale as i32),
+        }
+    }
+
+    /// Human-readable name for log messages
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("output {}", self.global_name))
+    }
+
+    /// Create the EGL surface, renderer and particle system once configured,
+    /// and propagate any size change from the compositor. While rain is
+    /// disabled no GL resources are created and the surface stays unmapped.
+    fn sync_gfx(&mut self, egl: &EglState, cfg: &RainConfig) -> Result<(), String> {
+        if !self.configured || self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        if self.gfx.is_none() && !cfg.enabled {
+            return Ok(());
+        }
+        let w = self.width as i32;
+        let h = self.height as i32;
+        let scale = self.scale();
+        // Buffer size in physical pixels; particles stay in logical pixels
+        let buf_w = (w as f64 * scale).round() as i32;
+        let buf_h = (h as f64 * scale).round() as i32;
+
+        match self.gfx.as_ref() {
+            Some(gfx) if gfx.width == w && gfx.height == h && gfx.scale == scale => {}
+            Some(_) => {
+                self.apply_scale(w, h, scale);
+                let gfx = self.gfx.as_mut().unwrap();
+                gfx.width = w;
+                gfx.height = h;
+                gfx.scale = scale;
+                gfx.egl_surface.resize(buf_w, buf_h);
+                egl.make_current(&gfx.egl_surface)?;
+                gfx.renderer.resize(w as f32, h as f32, scale as f32);
+                gfx.particles.resize(w as f32, h as f32);
+            }
+            None => {
+                eprintln!(
+                    "[raindesk overlay] Configured {}: {}x{} @ {}x",
+                    self.label(),
+                    w,
+                    h,
+                    scale
+                );
+                self.apply_scale(w, h, scale);
+                let egl_surface = egl.create_surface(&self.wl_surface, buf_w, buf_h)?;
+                egl.make_current(&egl_surface)?;
+                // Pacing comes from our own frame callbacks; never block in eglSwapBuffers
+                egl.set_swap_interval(0)?;
+                let renderer =
+                    Renderer::new(egl.create_gl_context(), w as f32, h as f32, scale as f32)?;
+                let seed = (self.global_name as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                let particles = ParticleSystem::new(w as f32, h as f32, cfg, seed);
+                self.gfx = Some(OutputGfx {
+                    egl_surface,
+                    renderer,
+                    particles,
+                    width: w,
+                    height: h,
+                    scale,
+                    frame_pending: false,
+                    frame_time: None,
+                    last_frame_time: None,
+                    last_render: None,
+                    timings: FrameTimings::default(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Release GL resources and attach a null buffer, unmapping the surface.
+    /// An unmapped layer surface must redo its initial commit, so do that
+    /// right away; the resulting configure leaves it ready to map again.
+    fn unmap(&mut self, egl: &EglState) {
+        if let Some(gfx) = self.gfx.take() {
+            gfx.destroy(egl);
+        }
+        self.wl_surface.attach(None, 0, 0);
+        self.wl_surface.commit();
+        self.configured = false;
+        self.wl_surface.commit();
+    }
+
+    /// Release GL resources and destroy the Wayland objects for this output
+    fn destroy(self, egl: &EglState) {
+        let output = self.into_output(egl);
+        if output.version() >= 3 {
+            output.release();
+        }
+    }
+
+    /// Release GL resources and destroy the surface, keeping the bound output
+    fn into_output(self, egl: &EglState) -> wl_output::WlOutput {
+        if let Some(gfx) = self.gfx {
+            gfx.destroy(egl);
+        }
+        if let Some(viewport) = self.viewport {
+            viewport.destroy();
+        }
+        if let Some(fractional_scale) = self.fractional_scale {
+            fractional_scale.destroy();
+        }
+        self.layer_surface.destroy();
+        self.wl_surface.destroy();
+        self.output
+    }
 }
 
-// Dispatch for layer surface events
-impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for OverlayState {
+/// Map the config layer to the layer-shell protocol enum
+fn shell_layer(layer: OverlayLayer) -> zwlr_layer_shell_v1::Layer {
+    match layer {
+        OverlayLayer::Background => zwlr_layer_shell_v1::Layer::Background,
+        OverlayLayer::Bottom => zwlr_layer_shell_v1::Layer::Bottom,
+        OverlayLayer::Top => zwlr_layer_shell_v1::Layer::Top,
+        OverlayLayer::Overlay => zwlr_layer_shell_v1::Layer::Overlay,
+    }
+}
+
+// Dispatch for layer surface events (user data is the output's global name)
+impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, u32> for OverlayState {
     fn event(
         state: &mut Self,
         surface: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
         event: zwlr_layer_surface_v1::Event,
-        _data: &(),
+        global_name: &u32,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
+        let Some(output) = state.output_mut(*global_name) else {
+            return;
+        };
         match event {
             zwlr_layer_surface_v1::Event::Configure {
                 serial,
@@ -212,13 +748,13 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for OverlayState {
             } => {
                 surface.ack_configure(serial);
                 if width > 0 && height > 0 {
-                    state.width = width;
-                    state.height = height;
+                    output.width = width;
+                    output.height = height;
                 }
-                state.configured = true;
+                output.configured = true;
             }
             zwlr_layer_surface_v1::Event::Closed => {
-                state.closed = true;
+                output.closed = true;
             }
             _ => {}
         }
@@ -242,16 +778,95 @@ impl Dispatch<zwlr_layer_shell_v1::ZwlrLayerShellV1, ()> for OverlayState {
 delegate_noop!(OverlayState: ignore wl_compositor::WlCompositor);
 delegate_noop!(OverlayState: ignore wl_surface::WlSurface);
 delegate_noop!(OverlayState: ignore wl_region::WlRegion);
-delegate_noop!(OverlayState: ignore wl_output::WlOutput);
 
+delegate_noop!(OverlayState: ignore wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1);
+delegate_noop!(OverlayState: ignore wp_viewporter::WpViewporter);
+delegate_noop!(OverlayState: ignore wp_viewport::WpViewport);
+
+// Dispatch for outputs (user data is the global name): connector name and integer scale
+impl Dispatch<wl_output::WlOutput, u32> for OverlayState {
+    fn event(
+        state: &mut Self,
+        _proxy: &wl_output::WlOutput,
+        event: wl_output::Event,
+        global_name: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state.output_mut(*global_name) else {
+            return;
+        };
+        match event {
+            wl_output::Event::Name { name } => output.name = Some(name),
+            wl_output::Event::Scale { factor } => output.output_scale = factor,
+            _ => {}
+        }
+    }
+}
+
+// Dispatch for frame callbacks (user data is the output's global name)
+impl Dispatch<wl_callback::WlCallback, u32> for OverlayState {
+    fn event(
+        state: &mut Self,
+        _proxy: &wl_callback::WlCallback,
+        event: wl_callback::Event,
+        global_name: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { callback_data } = event {
+            if let Some(gfx) = state.output_mut(*global_name).and_then(|o| o.gfx.as_mut()) {
+                gfx.frame_pending = false;
+                gfx.frame_time = Some(callback_data);
+            }
+        }
+    }
+}
+
+// Dispatch for fractional scale (user data is the output's global name)
+impl Dispatch<wp_fractional_scale_v1::WpFractionalScaleV1, u32> for OverlayState {
+    fn event(
+        state: &mut Self,
+        _proxy: &wp_fractional_scale_v1::WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        global_name: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            if let Some(output) = state.output_mut(*global_name) {
+                output.preferred_scale = Some(scale);
+            }
+        }
+    }
+}
+
+// Dispatch for registry events after the initial roundtrip (output hotplug)
 impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for OverlayState {
     fn event(
-        _state: &mut Self,
-        _proxy: &wl_registry::WlRegistry,
-        _event: wl_registry::Event,
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
         _data: &GlobalListContents,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == wl_output::WlOutput::interface().name => {
+                eprintln!("[raindesk overlay] Output added (global {})", name);
+                state.add_output(registry, name, version);
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                // Torn down after this dispatch, in `OverlayState::frame`
+                if let Some(output) = state.output_mut(name) {
+                    output.closed = true;
+                }
+            }
+            _ => {}
+        }
     }
 }
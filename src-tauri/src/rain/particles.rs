@@ -80,11 +80,13 @@ pub struct ParticleSystem {
 }
 
 impl ParticleSystem {
-    pub fn new(screen_w: f32, screen_h: f32, config: &RainConfig) -> Self {
+    /// Create a particle system. `seed` varies the random stream so that
+    /// several systems (one per output) don't rain in lockstep.
+    pub fn new(screen_w: f32, screen_h: f32, config: &RainConfig, seed: u64) -> Self {
         let mut sys = Self {
             drops: Vec::with_capacity(4096),
             splashes: Vec::with_capacity(1024),
-            rng: Rng::new(0xDEAD_DE5C_0000_0001_u64.wrapping_mul(42)),
+            rng: Rng::new(0xDEAD_DE5C_0000_0001_u64.wrapping_mul(42) ^ seed),
             screen_w,
             screen_h,
             spawn_rate: 0.0,
@@ -106,10 +108,21 @@ impl ParticleSystem {
         sys
     }
 
-    /// Resize the particle system when the screen dimensions change
+    /// Resize the particle system when the rain region changes size.
+    /// Live splashes move to the new ground line; drops outside the new
+    /// bounds are culled on the next update.
     pub fn resize(&mut self, w: f32, h: f32) {
         self.screen_w = w;
         self.screen_h = h;
+        let ground_y = self.ground_y();
+        for splash in &mut self.splashes {
+            splash.y = ground_y;
+        }
+    }
+
+    /// Y coordinate where drops land and splash: the bottom of the region
+    fn ground_y(&self) -> f32 {
+        self.screen_h - 2.0
     }
 
     /// Update cached config values from a RainConfig
@@ -157,6 +170,7 @@ impl ParticleSystem {
         // Update drops
         let screen_h = self.screen_h;
         let screen_w = self.screen_w;
+        let ground_y = self.ground_y();
         let splash_enabled = self.splash_enabled;
         let splash_intensity = self.splash_intensity;
 
@@ -186,7 +200,7 @@ impl ParticleSystem {
                     if (hash as f32 / 255.0) < splash_intensity {
                         new_splashes.push(Splash {
                             x: drop.x,
-                            y: screen_h - 2.0,
+                            y: ground_y,
                             radius: 0.0,
                             max_radius: drop.width * 3.0 + 2.0,
                             alpha: 0.8,
@@ -236,6 +250,11 @@ impl ParticleSystem {
         });
     }
 
+    /// Rain is disabled and the last drop and splash have faded out
+    pub fn is_idle(&self) -> bool {
+        !self.enabled && self.drops.is_empty() && self.splashes.is_empty()
+    }
+
     /// Get the RGBA color as [f32; 4]
     pub fn color(&self) -> [f32; 4] {
         [self.color_r, self.color_g, self.color_b, self.color_a * self.opacity]
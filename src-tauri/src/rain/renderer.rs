@@ -120,18 +120,20 @@ pub struct Renderer {
     splash_projection_loc: glow::UniformLocation,
     splash_color_loc: glow::UniformLocation,
 
+    // Logical size; the viewport is this times `scale` in buffer pixels
     width: f32,
     height: f32,
+    scale: f32,
 }
 
 impl Renderer {
-    pub fn new(gl: glow::Context, width: f32, height: f32) -> Result<Self, String> {
+    /// `width` and `height` are logical pixels, `scale` is the buffer scale
+    pub fn new(gl: glow::Context, width: f32, height: f32, scale: f32) -> Result<Self, String> {
         unsafe {
             gl.enable(glow::BLEND);
             gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
             gl.disable(glow::DEPTH_TEST);
             gl.clear_color(0.0, 0.0, 0.0, 0.0);
-            gl.viewport(0, 0, width as i32, height as i32);
 
             // === Raindrop program ===
             let drop_program = compile_program(&gl, RAINDROP_VERT, RAINDROP_FRAG)?;
@@ -261,19 +263,33 @@ impl Renderer {
                 splash_color_loc,
                 width,
                 height,
+                scale,
             };
+            renderer.update_viewport();
             renderer.update_projection();
             Ok(renderer)
         }
     }
 
-    pub fn resize(&mut self, width: f32, height: f32) {
+    /// `GL_RENDERER` string of the current context, e.g. the GPU and driver
+    pub fn gl_renderer(&self) -> String {
+        unsafe { self.gl.get_parameter_string(glow::RENDERER) }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32, scale: f32) {
         self.width = width;
         self.height = height;
+        self.scale = scale;
+        self.update_viewport();
+        self.update_projection();
+    }
+
+    fn update_viewport(&self) {
+        let buf_w = (self.width * self.scale).round() as i32;
+        let buf_h = (self.height * self.scale).round() as i32;
         unsafe {
-            self.gl.viewport(0, 0, width as i32, height as i32);
+            self.gl.viewport(0, 0, buf_w, buf_h);
         }
-        self.update_projection();
     }
 
     fn update_projection(&mut self) {
@@ -291,6 +307,8 @@ impl Renderer {
 
     /// Render all particles
     pub fn render(&self, particles: &ParticleSystem) {
+        // The GL context is shared between outputs, so the viewport is per-frame state
+        self.update_viewport();
         unsafe {
             self.gl.clear(glow::COLOR_BUFFER_BIT);
         }
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Overlay performance snapshot, sent to the app about once a second
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OverlayStats {
+    /// `GL_RENDERER` of the overlay's GL context (GPU and driver)
+    pub gl_renderer: Option<String>,
+    pub outputs: Vec<OutputStats>,
+}
+
+/// Stats for one output over the last reporting window
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputStats {
+    /// Connector name (e.g. "DP-1"), or the registry name if not reported
+    pub name: String,
+    /// Logical surface size
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+    /// Surface is mapped and drawing rain
+    pub active: bool,
+    pub fps: f32,
+    /// CPU time per frame (simulation, draw calls and swap), in milliseconds
+    pub frame_time_p50_ms: f32,
+    pub frame_time_p95_ms: f32,
+    pub frame_time_p99_ms: f32,
+    pub frame_time_max_ms: f32,
+    pub drops: usize,
+    pub splashes: usize,
+}
+
+/// Frame timings collected for one output between two reports
+#[derive(Default)]
+pub struct FrameTimings {
+    frame_times_ms: Vec<f32>,
+}
+
+/// Frame rate and frame time percentiles for a reporting window
+#[derive(Default)]
+pub struct FrameSummary {
+    pub fps: f32,
+    pub p50_ms: f32,
+    pub p95_ms: f32,
+    pub p99_ms: f32,
+    pub max_ms: f32,
+}
+
+impl FrameTimings {
+    /// Record how long one frame took
+    pub fn record(&mut self, frame_time: Duration) {
+        self.frame_times_ms.push(frame_time.as_secs_f32() * 1000.0);
+    }
+
+    /// Summarize the frames recorded over `window` and start a new window
+    pub fn take(&mut self, window: Duration) -> FrameSummary {
+        let mut times = std::mem::take(&mut self.frame_times_ms);
+        times.sort_by(|a, b| a.total_cmp(b));
+        let secs = window.as_secs_f32();
+        FrameSummary {
+            fps: if secs > 0.0 { times.len() as f32 / secs } else { 0.0 },
+            p50_ms: percentile(&times, 0.50),
+            p95_ms: percentile(&times, 0.95),
+            p99_ms: percentile(&times, 0.99),
+            max_ms: times.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted samples (0 when empty)
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_window_is_all_zero() {
+        let summary = FrameTimings::default().take(Duration::from_secs(1));
+        assert_eq!(summary.fps, 0.0);
+        assert_eq!(summary.p50_ms, 0.0);
+        assert_eq!(summary.p99_ms, 0.0);
+        assert_eq!(summary.max_ms, 0.0);
+    }
+
+    #[test]
+    fn single_sample_is_every_percentile() {
+        let mut timings = FrameTimings::default();
+        timings.record(Duration::from_millis(4));
+        let summary = timings.take(Duration::from_secs(2));
+        assert_eq!(summary.fps, 0.5);
+        assert_eq!(summary.p50_ms, 4.0);
+        assert_eq!(summary.p99_ms, 4.0);
+        assert_eq!(summary.max_ms, 4.0);
+    }
+
+    #[test]
+    fn percentiles_of_a_known_distribution() {
+        let mut timings = FrameTimings::default();
+        // 1..=100 ms, recorded out of order
+        for ms in (1..=100).rev() {
+            timings.record(Duration::from_millis(ms));
+        }
+        let summary = timings.take(Duration::from_secs(1));
+        assert_eq!(summary.fps, 100.0);
+        assert_eq!(summary.p50_ms, 50.0);
+        assert_eq!(summary.p95_ms, 95.0);
+        assert_eq!(summary.p99_ms, 99.0);
+        assert_eq!(summary.max_ms, 100.0);
+
+        // The window starts over after each report
+        assert_eq!(timings.take(Duration::from_secs(1)).fps, 0.0);
+    }
+}
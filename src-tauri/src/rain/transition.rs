@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use crate::config::{Easing, RainColor, RainConfig};
+
+/// Animates the look of the rain from one config to another. Numeric
+/// fields and the color are interpolated; switches take the new value
+/// right away, except that splashes being turned off fade out first.
+pub struct Transition {
+    from: RainConfig,
+    to: RainConfig,
+    /// `to` with the animated fields as of the latest `step`
+    current: RainConfig,
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Transition {
+    /// Start a transition at `now`. `from` is what's on screen, which may be
+    /// partway through an earlier transition.
+    pub fn new(from: RainConfig, to: RainConfig, duration: Duration, now: Instant) -> Self {
+        Self {
+            easing: to.transition.easing,
+            current: to.clone(),
+            from,
+            to,
+            start: now,
+            duration,
+        }
+    }
+
+    /// Config to draw at `now`
+    pub fn step(&mut self, now: Instant) -> &RainConfig {
+        let t = match self.duration.as_secs_f32() {
+            d if d > 0.0 => (now.duration_since(self.start).as_secs_f32() / d).min(1.0),
+            _ => 1.0,
+        };
+        let t = ease(self.easing, t);
+        let (from, to) = (&self.from, &self.to);
+        let c = &mut self.current;
+        c.intensity = lerp(from.intensity, to.intensity, t);
+        c.speed = lerp(from.speed, to.speed, t);
+        c.angle = lerp(from.angle, to.angle, t);
+        c.drop_length = lerp(from.drop_length, to.drop_length, t);
+        c.drop_width = lerp(from.drop_width, to.drop_width, t);
+        c.opacity = lerp(from.opacity, to.opacity, t);
+        c.color = lerp_color(&from.color, &to.color, t);
+        // Splashes fade in from and out to nothing when switched
+        c.splash_enabled = from.splash_enabled || to.splash_enabled;
+        c.splash_intensity = lerp(splash_amount(from), splash_amount(to), t);
+        if t >= 1.0 {
+            c.splash_enabled = to.splash_enabled;
+            c.splash_intensity = to.splash_intensity;
+        }
+        c
+    }
+
+    /// Whether `now` is past the end, so the target config can be used as is
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now.duration_since(self.start) >= self.duration
+    }
+}
+
+/// Progress along the easing curve for linear progress `t` in 0-1
+fn ease(easing: Easing, t: f32) -> f32 {
+    match easing {
+        Easing::Linear => t,
+        Easing::EaseIn => t * t * t,
+        Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+        Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+        Easing::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_color(a: &RainColor, b: &RainColor, t: f32) -> RainColor {
+    let channel = |a: u8, b: u8| lerp(a as f32, b as f32, t).round() as u8;
+    RainColor {
+        r: channel(a.r, b.r),
+        g: channel(a.g, b.g),
+        b: channel(a.b, b.b),
+        a: channel(a.a, b.a),
+    }
+}
+
+fn splash_amount(config: &RainConfig) -> f32 {
+    if config.splash_enabled {
+        config.splash_intensity
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+
+    fn color(r: u8, g: u8, b: u8, a: u8) -> RainColor {
+        RainColor { r, g, b, a }
+    }
+
+    #[test]
+    fn easing_starts_at_zero_and_ends_at_one() {
+        for easing in EASINGS {
+            assert_eq!(ease(easing, 0.0), 0.0, "{:?}", easing);
+            assert_eq!(ease(easing, 1.0), 1.0, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easing_never_goes_backwards() {
+        for easing in EASINGS {
+            let mut previous = 0.0;
+            for i in 1..=1000 {
+                let eased = ease(easing, i as f32 / 1000.0);
+                assert!(eased >= previous, "{:?} at {}", easing, i);
+                assert!(eased <= 1.0, "{:?} at {}", easing, i);
+                previous = eased;
+            }
+        }
+    }
+
+    #[test]
+    fn transition_starts_at_from_and_ends_at_to() {
+        let from = RainConfig::default();
+        let to = RainConfig {
+            intensity: from.intensity + 0.3,
+            angle: -20.0,
+            color: color(10, 20, 30, 40),
+            ..RainConfig::default()
+        };
+        let start = Instant::now();
+        let duration = Duration::from_millis(500);
+        for easing in EASINGS {
+            let mut to = to.clone();
+            to.transition.easing = easing;
+            let mut transition = Transition::new(from.clone(), to.clone(), duration, start);
+
+            let first = transition.step(start);
+            assert_eq!(first.intensity, from.intensity);
+            assert_eq!(first.angle, from.angle);
+            assert_eq!(first.color, from.color);
+
+            let last = transition.step(start + duration * 2);
+            assert_eq!(last.intensity, to.intensity);
+            assert_eq!(last.angle, to.angle);
+            assert_eq!(last.color, to.color);
+            assert!(transition.is_finished(start + duration));
+        }
+    }
+
+    #[test]
+    fn color_channels_stay_in_range() {
+        let black = color(0, 0, 0, 0);
+        let white = color(255, 255, 255, 255);
+        assert_eq!(lerp_color(&black, &white, 0.0), black);
+        assert_eq!(lerp_color(&black, &white, 1.0), white);
+        assert_eq!(lerp_color(&white, &black, 1.0), black);
+        // Rounding never wraps a channel past 255 or below 0
+        let (mut up, mut down) = (black.clone(), white.clone());
+        for i in 1..=1000 {
+            let t = i as f32 / 1000.0;
+            let (next_up, next_down) =
+                (lerp_color(&black, &white, t), lerp_color(&white, &black, t));
+            assert!(next_up.r >= up.r && next_down.r <= down.r, "t = {}", t);
+            (up, down) = (next_up, next_down);
+        }
+    }
+}
@@ -0,0 +1,614 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use thiserror::Error;
+
+/// Group a field belongs to in the control panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    Rain,
+    Splash,
+    Overlay,
+    Transition,
+    Region,
+    Pomodoro,
+}
+
+/// Allowed range of a numeric config field. The same table drives
+/// validation, lenient clamping and the sliders in the control panel.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FieldSpec {
+    /// Dotted path of the field, e.g. "region.margin_top"
+    pub field: &'static str,
+    pub label: &'static str,
+    pub section: Section,
+    pub min: f64,
+    pub max: f64,
+    /// Slider step
+    pub step: f64,
+    /// Unit shown next to the value; "%" fields hold a 0-1 fraction
+    pub unit: &'static str,
+    /// Value outside the range that is accepted as "off" (max_fps = 0)
+    pub off_value: Option<f64>,
+    /// Field may be unset (`null`)
+    pub optional: bool,
+}
+
+const fn field(
+    field: &'static str,
+    label: &'static str,
+    section: Section,
+    min: f64,
+    max: f64,
+    step: f64,
+    unit: &'static str,
+) -> FieldSpec {
+    FieldSpec {
+        field,
+        label,
+        section,
+        min,
+        max,
+        step,
+        unit,
+        off_value: None,
+        optional: false,
+    }
+}
+
+pub const INTENSITY: FieldSpec = field("intensity", "Intensity", Section::Rain, 0.0, 1.0, 0.01, "");
+pub const SPEED: FieldSpec = field("speed", "Speed", Section::Rain, 0.5, 5.0, 0.1, "x");
+pub const ANGLE: FieldSpec = field("angle", "Angle", Section::Rain, -60.0, 60.0, 1.0, "°");
+pub const DROP_LENGTH: FieldSpec = field(
+    "drop_length",
+    "Drop Length",
+    Section::Rain,
+    5.0,
+    100.0,
+    1.0,
+    "px",
+);
+pub const DROP_WIDTH: FieldSpec = field(
+    "drop_width",
+    "Drop Width",
+    Section::Rain,
+    1.0,
+    10.0,
+    0.5,
+    "px",
+);
+pub const OPACITY: FieldSpec = field("opacity", "Opacity", Section::Rain, 0.0, 1.0, 0.01, "%");
+pub const SPLASH_INTENSITY: FieldSpec = field(
+    "splash_intensity",
+    "Splash Intensity",
+    Section::Splash,
+    0.0,
+    1.0,
+    0.01,
+    "%",
+);
+pub const MAX_FPS: FieldSpec = FieldSpec {
+    off_value: Some(0.0),
+    ..field(
+        "max_fps",
+        "Frame Rate Cap",
+        Section::Overlay,
+        10.0,
+        500.0,
+        1.0,
+        "fps",
+    )
+};
+pub const TRANSITION_DURATION: FieldSpec = field(
+    "transition.duration_ms",
+    "Transition Duration",
+    Section::Transition,
+    0.0,
+    10_000.0,
+    50.0,
+    "ms",
+);
+pub const MARGIN_TOP: FieldSpec = field(
+    "region.margin_top",
+    "Top Margin",
+    Section::Region,
+    0.0,
+    10_000.0,
+    1.0,
+    "px",
+);
+pub const MARGIN_RIGHT: FieldSpec = field(
+    "region.margin_right",
+    "Right Margin",
+    Section::Region,
+    0.0,
+    10_000.0,
+    1.0,
+    "px",
+);
+pub const MARGIN_BOTTOM: FieldSpec = field(
+    "region.margin_bottom",
+    "Bottom Margin",
+    Section::Region,
+    0.0,
+    10_000.0,
+    1.0,
+    "px",
+);
+pub const MARGIN_LEFT: FieldSpec = field(
+    "region.margin_left",
+    "Left Margin",
+    Section::Region,
+    0.0,
+    10_000.0,
+    1.0,
+    "px",
+);
+pub const WIDTH: FieldSpec = FieldSpec {
+    optional: true,
+    ..field(
+        "region.width",
+        "Width",
+        Section::Region,
+        1.0,
+        16_384.0,
+        1.0,
+        "px",
+    )
+};
+pub const HEIGHT: FieldSpec = FieldSpec {
+    optional: true,
+    ..field(
+        "region.height",
+        "Height",
+        Section::Region,
+        1.0,
+        16_384.0,
+        1.0,
+        "px",
+    )
+};
+pub const WORK_DURATION: FieldSpec = field(
+    "pomodoro.work_duration_secs",
+    "Work",
+    Section::Pomodoro,
+    60.0,
+    10_800.0,
+    60.0,
+    "s",
+);
+pub const SHORT_BREAK_DURATION: FieldSpec = field(
+    "pomodoro.short_break_duration_secs",
+    "Short Break",
+    Section::Pomodoro,
+    60.0,
+    10_800.0,
+    60.0,
+    "s",
+);
+pub const LONG_BREAK_DURATION: FieldSpec = field(
+    "pomodoro.long_break_duration_secs",
+    "Long Break",
+    Section::Pomodoro,
+    60.0,
+    10_800.0,
+    60.0,
+    "s",
+);
+pub const SESSIONS_UNTIL_LONG_BREAK: FieldSpec = field(
+    "pomodoro.sessions_until_long_break",
+    "Sessions Until Long Break",
+    Section::Pomodoro,
+    1.0,
+    12.0,
+    1.0,
+    "",
+);
+pub const DAILY_GOAL: FieldSpec = field(
+    "pomodoro.daily_goal_mins",
+    "Daily Focus Goal",
+    Section::Pomodoro,
+    5.0,
+    720.0,
+    5.0,
+    "min",
+);
+
+/// Every numeric field, in the order the control panel shows them
+pub const FIELDS: &[FieldSpec] = &[
+    INTENSITY,
+    SPEED,
+    ANGLE,
+    DROP_LENGTH,
+    DROP_WIDTH,
+    OPACITY,
+    SPLASH_INTENSITY,
+    MAX_FPS,
+    TRANSITION_DURATION,
+    MARGIN_TOP,
+    MARGIN_RIGHT,
+    MARGIN_BOTTOM,
+    MARGIN_LEFT,
+    WIDTH,
+    HEIGHT,
+    WORK_DURATION,
+    SHORT_BREAK_DURATION,
+    LONG_BREAK_DURATION,
+    SESSIONS_UNTIL_LONG_BREAK,
+    DAILY_GOAL,
+];
+
+impl FieldSpec {
+    fn allows(&self, value: f64) -> bool {
+        (value >= self.min && value <= self.max) || self.off_value == Some(value)
+    }
+
+    fn range_message(&self, value: impl fmt::Display) -> String {
+        match self.off_value {
+            Some(off) => format!(
+                "must be {} or between {} and {}, got {}",
+                off, self.min, self.max, value
+            ),
+            None => format!(
+                "must be between {} and {}, got {}",
+                self.min, self.max, value
+            ),
+        }
+    }
+}
+
+/// How out-of-range values are handled when a config is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    /// Reject the whole config if any field is out of range
+    #[default]
+    Strict,
+    /// Clamp out-of-range fields and report which ones were changed
+    Lenient,
+}
+
+/// A config field that is out of its allowed range, or that a config sent
+/// by the UI is missing, shouldn't have, or holds the wrong type in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Dotted path of the field, e.g. "region.margin_top"
+    pub field: String,
+    pub message: String,
+    /// Allowed range, for numeric fields
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The rejected value
+    pub value: Value,
+    /// What the value was replaced with (lenient mode only)
+    pub clamped_to: Option<Value>,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+/// A config rejected in strict mode, with every offending field
+#[derive(Error, Debug, Clone, Serialize)]
+#[error("Invalid config: {}", describe(.errors))]
+pub struct ValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    pub fn new(mut errors: Vec<FieldError>) -> Self {
+        for error in &mut errors {
+            error.clamped_to = None;
+        }
+        Self { errors }
+    }
+}
+
+/// One line listing every field error, for logs and plain-text errors
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Numeric types config fields are stored as
+pub trait Number: Copy + fmt::Display {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! impl_number {
+    ($($ty:ty),*) => {
+        $(impl Number for $ty {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value as $ty
+            }
+        })*
+    };
+}
+
+impl_number!(f32, u32, i32);
+
+/// JSON form of a number as it's displayed, so an f32 0.7 doesn't turn
+/// into 0.699999988
+fn json_number(value: impl fmt::Display) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
+
+/// Walks a config, clamping each field into range and recording the ones
+/// that had to change
+#[derive(Default)]
+pub struct RangeCheck {
+    errors: Vec<FieldError>,
+}
+
+impl RangeCheck {
+    /// Clamp a numeric field; NaN becomes the minimum
+    pub fn number<T: Number>(&mut self, value: &mut T, spec: &FieldSpec) {
+        let old = *value;
+        let v = old.to_f64();
+        if spec.allows(v) {
+            return;
+        }
+        let fixed = if v.is_nan() {
+            spec.min
+        } else {
+            v.clamp(spec.min, spec.max)
+        };
+        *value = T::from_f64(fixed);
+        self.errors.push(FieldError {
+            field: spec.field.to_string(),
+            message: spec.range_message(old),
+            min: Some(spec.min),
+            max: Some(spec.max),
+            value: json_number(old),
+            clamped_to: Some(json_number(*value)),
+        });
+    }
+
+    /// Clamp an optional numeric field if it's set
+    pub fn optional<T: Number>(&mut self, value: &mut Option<T>, spec: &FieldSpec) {
+        if let Some(value) = value {
+            self.number(value, spec);
+        }
+    }
+
+    /// Turn on a flag that must be set, explaining why
+    pub fn require(&mut self, field: &str, value: &mut bool, reason: &str) {
+        if *value {
+            return;
+        }
+        *value = true;
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: format!("must be true {}", reason),
+            min: None,
+            max: None,
+            value: Value::Bool(false),
+            clamped_to: Some(Value::Bool(true)),
+        });
+    }
+
+    pub fn into_errors(self) -> Vec<FieldError> {
+        self.errors
+    }
+}
+
+/// Fields of a config sent by the UI that don't match `expected`, a
+/// complete config: missing, unknown, or of the wrong JSON type. A config
+/// file may leave fields out, but a payload that does has a key wrong.
+/// Fields that are `null` in `expected` may hold anything.
+pub fn shape_errors(payload: &Value, expected: &Value) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check_shape("", payload, expected, &mut errors);
+    errors
+}
+
+fn check_shape(path: &str, payload: &Value, expected: &Value, errors: &mut Vec<FieldError>) {
+    let shape_error = |message: String, value: &Value| FieldError {
+        field: path.to_string(),
+        message,
+        min: None,
+        max: None,
+        value: value.clone(),
+        clamped_to: None,
+    };
+    match (payload, expected) {
+        (_, Value::Null) => {}
+        (Value::Object(fields), Value::Object(expected_fields)) => {
+            for (key, expected) in expected_fields {
+                let field = join_path(path, key);
+                match fields.get(key) {
+                    Some(value) => check_shape(&field, value, expected, errors),
+                    None => errors.push(FieldError {
+                        field,
+                        ..shape_error("is missing".to_string(), &Value::Null)
+                    }),
+                }
+            }
+            for (key, value) in fields {
+                if !expected_fields.contains_key(key) {
+                    errors.push(FieldError {
+                        field: join_path(path, key),
+                        ..shape_error("is not a setting".to_string(), value)
+                    });
+                }
+            }
+        }
+        _ if json_type(payload) != json_type(expected) => {
+            errors.push(shape_error(
+                format!("must be {}", json_type(expected)),
+                payload,
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// The first field of `payload` that `accepts` rejects when put into
+/// `expected` on its own, so a type error can be pinned to a field
+pub fn rejected_field(
+    payload: &Value,
+    expected: &Value,
+    accepts: impl Fn(Value) -> bool,
+) -> Option<(String, Value)> {
+    let mut leaves = Vec::new();
+    collect_leaves(String::new(), payload, expected, &mut leaves);
+    leaves.into_iter().find(|(path, value)| {
+        let mut candidate = expected.clone();
+        if let Some(slot) = candidate.pointer_mut(&format!("/{}", path.replace('.', "/"))) {
+            *slot = value.clone();
+        }
+        !accepts(candidate)
+    })
+}
+
+fn collect_leaves(
+    path: String,
+    payload: &Value,
+    expected: &Value,
+    leaves: &mut Vec<(String, Value)>,
+) {
+    match (payload, expected) {
+        (Value::Object(fields), Value::Object(expected_fields)) => {
+            for (key, value) in fields {
+                if let Some(expected) = expected_fields.get(key) {
+                    collect_leaves(join_path(&path, key), value, expected, leaves);
+                }
+            }
+        }
+        _ => leaves.push((path, payload.clone())),
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "true or false",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_in_range_are_left_alone() {
+        let mut check = RangeCheck::default();
+        let (mut low, mut high) = (INTENSITY.min as f32, INTENSITY.max as f32);
+        check.number(&mut low, &INTENSITY);
+        check.number(&mut high, &INTENSITY);
+        let mut fps = 0u32;
+        check.number(&mut fps, &MAX_FPS);
+        let mut width = None::<u32>;
+        check.optional(&mut width, &WIDTH);
+        assert_eq!((low, high, fps, width), (0.0, 1.0, 0, None));
+        assert!(check.into_errors().is_empty());
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped_and_reported() {
+        let mut check = RangeCheck::default();
+        let mut angle = 90.0f32;
+        check.number(&mut angle, &ANGLE);
+        let mut width = Some(0u32);
+        check.optional(&mut width, &WIDTH);
+        assert_eq!((angle, width), (60.0, Some(1)));
+
+        let errors = check.into_errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "angle");
+        assert_eq!(errors[0].message, "must be between -60 and 60, got 90");
+        assert_eq!(errors[0].value, json!(90.0));
+        assert_eq!(errors[0].clamped_to, Some(json!(60.0)));
+        assert_eq!(errors[1].field, "region.width");
+    }
+
+    #[test]
+    fn nan_becomes_the_minimum() {
+        let mut check = RangeCheck::default();
+        let mut speed = f32::NAN;
+        check.number(&mut speed, &SPEED);
+        assert_eq!(speed, SPEED.min as f32);
+        let errors = check.into_errors();
+        assert_eq!(errors[0].field, "speed");
+        // NaN has no JSON form
+        assert_eq!(errors[0].value, Value::Null);
+    }
+
+    #[test]
+    fn strict_errors_drop_the_clamped_values() {
+        let mut check = RangeCheck::default();
+        let mut fps = 5u32;
+        check.number(&mut fps, &MAX_FPS);
+        let errors = check.into_errors();
+        assert_eq!(errors[0].message, "must be 0 or between 10 and 500, got 5");
+        // Lenient mode reports what the value became; a strict rejection does not
+        assert_eq!(errors[0].clamped_to, Some(json!(10.0)));
+        let error = ValidationError::new(errors);
+        assert_eq!(error.errors[0].clamped_to, None);
+        assert_eq!(
+            error.to_string(),
+            "Invalid config: max_fps must be 0 or between 10 and 500, got 5"
+        );
+    }
+
+    #[test]
+    fn shape_errors_report_unknown_missing_and_mistyped_fields() {
+        let expected = json!({
+            "intensity": 0.5,
+            "region": { "width": null, "margin_top": 0 },
+            "enabled": true,
+        });
+        let payload = json!({
+            "intensity": "0.5",
+            "region": { "width": 300, "margin_top": 0, "margin_topp": 4 },
+            "colour": "blue",
+        });
+        let mut errors: Vec<_> = shape_errors(&payload, &expected)
+            .into_iter()
+            .map(|e| (e.field, e.message))
+            .collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            [
+                ("colour".to_string(), "is not a setting".to_string()),
+                ("enabled".to_string(), "is missing".to_string()),
+                ("intensity".to_string(), "must be a number".to_string()),
+                (
+                    "region.margin_topp".to_string(),
+                    "is not a setting".to_string()
+                ),
+            ]
+        );
+        assert!(shape_errors(&expected, &expected).is_empty());
+    }
+}
@@ -1,35 +1,127 @@
-use crate::config::RainConfig;
-use crate::pomodoro::PomodoroState;
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use crate::clock::{self, Now};
+use crate::config::{ConfigIssue, RainConfig, CONFIG_VERSION};
+use crate::config_writer::ConfigWriter;
+use crate::playlist::{Playlist, PlaylistEntry, PlaylistError, PlaylistState, PlaylistStatus};
+use crate::pomodoro::{PomodoroSettings, PomodoroState};
+use crate::pomodoro_history;
+use crate::presets;
+use crate::profiles::{self, ProfileList, DEFAULT_PROFILE};
+use crate::rain::stats::OverlayStats;
+use crate::schema::{self, FieldError, ValidationError, ValidationMode};
+use calloop::channel;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use thiserror::Error;
 
 /// Signals sent from Tauri commands to the overlay thread
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum OverlaySignal {
-    /// Config has been updated, overlay should re-read from Arc
-    ConfigChanged,
+    /// Config has been updated, overlay should re-read from Arc. The change
+    /// is animated over `transition_ms` if set, otherwise over the
+    /// config's transition duration.
+    ConfigChanged { transition_ms: Option<u32> },
     /// Shutdown the overlay thread
     Shutdown,
 }
 
+/// Overlay thread health, as reported by the supervisor
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum OverlayHealth {
+    /// Connecting to Wayland and initializing EGL
+    Starting,
+    /// Event loop is running
+    Running,
+    /// The overlay failed and will be restarted after a backoff
+    Restarting {
+        attempt: u32,
+        reason: String,
+        retry_in_ms: u64,
+    },
+    /// The overlay kept failing and the supervisor gave up
+    Failed { reason: String },
+    /// The overlay shut down cleanly
+    Stopped,
+}
+
+/// Config after an update, with the fields clamped in lenient mode
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigUpdate {
+    pub config: RainConfig,
+    pub clamped: Vec<FieldError>,
+}
+
+/// Why a config update was not applied
+#[derive(Error, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ConfigUpdateError {
+    /// Fields out of range (strict mode), or missing, unknown or mistyped
+    #[error(transparent)]
+    Invalid(ValidationError),
+    /// The active profile's file can't be located
+    #[error("{message}")]
+    Profile { message: String },
+}
+
 /// Application state shared across Tauri commands
 pub struct AppState {
     /// Rain configuration (shared with overlay thread via Arc)
     pub config: Arc<Mutex<RainConfig>>,
+    /// Why the config file couldn't be loaded at startup, if it couldn't
+    pub config_issue: Mutex<Option<ConfigIssue>>,
+    /// Background writer that persists config changes
+    pub config_writer: ConfigWriter,
+    /// Profile whose file the config is loaded from and saved to
+    pub active_profile: Mutex<String>,
     /// Pomodoro timer state
     pub pomodoro: Mutex<PomodoroState>,
-    /// Channel to signal the overlay thread
-    pub overlay_tx: Mutex<Option<mpsc::Sender<OverlaySignal>>>,
+    /// Held while the timer is saved and its events logged, so that happens
+    /// in the order of the changes without keeping `pomodoro` locked
+    /// during disk I/O. Taken before `pomodoro` is released.
+    pomodoro_io: Mutex<()>,
+    /// Wakes the pomodoro clock thread when the timer is changed
+    pub pomodoro_tx: Mutex<Option<mpsc::Sender<()>>>,
+    /// Preset playlist and where playback is
+    pub playlist: Mutex<PlaylistState>,
+    /// Wakes the playlist thread when the playback position changes
+    pub playlist_tx: Mutex<Option<mpsc::Sender<()>>>,
+    /// Channel to signal the overlay thread (wakes its event loop)
+    pub overlay_tx: Mutex<Option<channel::Sender<OverlaySignal>>>,
+    /// Latest overlay health published by the supervisor
+    pub overlay_health: Mutex<OverlayHealth>,
+    /// Latest performance snapshot from the overlay thread
+    pub overlay_stats: Mutex<OverlayStats>,
 }
 
 impl AppState {
     pub fn new() -> Self {
-        let config = RainConfig::load().unwrap_or_default();
+        let active_profile = profiles::load_active();
+        let (config, config_issue) = match profiles::profile_path(&active_profile) {
+            Ok(path) => RainConfig::load_or_recover(&path),
+            Err(e) => {
+                let issue = ConfigIssue {
+                    message: format!("Settings can't be saved: {}", e),
+                    backup_path: None,
+                };
+                (RainConfig::default(), Some(issue))
+            }
+        };
+        let mut pomodoro = PomodoroState::restore(&config.pomodoro, clock::now());
+        pomodoro_history::record(&pomodoro.take_events());
         Self {
             config: Arc::new(Mutex::new(config)),
-            pomodoro: Mutex::new(PomodoroState::new()),
+            config_issue: Mutex::new(config_issue),
+            config_writer: ConfigWriter::spawn(),
+            active_profile: Mutex::new(active_profile),
+            pomodoro: Mutex::new(pomodoro),
+            pomodoro_io: Mutex::new(()),
+            pomodoro_tx: Mutex::new(None),
+            playlist: Mutex::new(PlaylistState::load()),
+            playlist_tx: Mutex::new(None),
             overlay_tx: Mutex::new(None),
+            overlay_health: Mutex::new(OverlayHealth::Starting),
+            overlay_stats: Mutex::new(OverlayStats::default()),
         }
     }
 
@@ -38,38 +130,324 @@ impl AppState {
         self.config.lock().unwrap().clone()
     }
 
+    /// Config file of the active profile
+    pub fn active_profile_path(&self) -> Result<PathBuf, String> {
+        profiles::profile_path(&self.active_profile.lock().unwrap()).map_err(|e| e.to_string())
+    }
+
     /// Signal the overlay thread that config changed
-    fn notify_overlay(&self) {
+    fn notify_overlay(&self, transition_ms: Option<u32>) {
         if let Some(tx) = self.overlay_tx.lock().unwrap().as_ref() {
-            let _ = tx.send(OverlaySignal::ConfigChanged);
+            let _ = tx.send(OverlaySignal::ConfigChanged { transition_ms });
         }
     }
 
-    /// Update config and save to disk
-    pub fn update_config<F>(&self, f: F) -> Result<RainConfig, String>
+    /// Hand changed pomodoro durations to the running timer
+    fn sync_pomodoro(&self, old: &PomodoroSettings, new: &PomodoroSettings) {
+        if old != new {
+            self.change_pomodoro(|pomodoro, now| pomodoro.apply_settings(new, now));
+        }
+    }
+
+    /// Change the pomodoro timer as of the current time, save it, log what
+    /// happened to the history, and let the clock thread follow (start or
+    /// stop ticking). Returns the new timer state.
+    pub fn change_pomodoro<F>(&self, f: F) -> PomodoroState
+    where
+        F: FnOnce(&mut PomodoroState, Now),
+    {
+        let now = clock::now();
+        let mut pomodoro = self.pomodoro.lock().unwrap();
+        f(&mut pomodoro, now);
+        let snapshot = self.persist_pomodoro(pomodoro, Some(now));
+        if let Some(tx) = self.pomodoro_tx.lock().unwrap().as_ref() {
+            let _ = tx.send(());
+        }
+        snapshot
+    }
+
+    /// Save the pomodoro timer as it is now, for the next launch
+    pub fn save_pomodoro(&self) {
+        let pomodoro = self.pomodoro.lock().unwrap();
+        self.persist_pomodoro(pomodoro, Some(clock::now()));
+    }
+
+    /// Release the locked timer, then save it as of `save` (if given) and
+    /// log its new events to the history. Returns the timer as it was.
+    pub fn persist_pomodoro(
+        &self,
+        mut pomodoro: MutexGuard<'_, PomodoroState>,
+        save: Option<Now>,
+    ) -> PomodoroState {
+        let saved = save.map(|now| pomodoro.take_save(now));
+        let events = pomodoro.take_events();
+        let snapshot = pomodoro.clone();
+        let io = self.pomodoro_io.lock().unwrap();
+        drop(pomodoro);
+        if let Some(saved) = saved {
+            saved.write();
+        }
+        pomodoro_history::record(&events);
+        drop(io);
+        snapshot
+    }
+
+    /// Apply a change to a copy of the config, check it according to
+    /// `mode`, then adopt it and queue it to be saved to the active profile
+    fn change_config<F>(
+        &self,
+        f: F,
+        mode: ValidationMode,
+        transition_ms: Option<u32>,
+    ) -> Result<ConfigUpdate, ConfigUpdateError>
     where
         F: FnOnce(&mut RainConfig),
     {
+        // Lock order: active profile, then config
+        let profile = self.active_profile.lock().unwrap();
+        let path = profiles::profile_path(&profile).map_err(|e| ConfigUpdateError::Profile {
+            message: e.to_string(),
+        })?;
         let mut config = self.config.lock().unwrap();
-        f(&mut config);
-        config.clamp();
-        config.save().map_err(|e| e.to_string())?;
-        let result = config.clone();
+        let mut new_config = config.clone();
+        f(&mut new_config);
+        let (new_config, clamped) = new_config
+            .checked(mode)
+            .map_err(ConfigUpdateError::Invalid)?;
+        let old_config = std::mem::replace(&mut *config, new_config.clone());
+        // Queued under the lock so snapshots reach the writer in order
+        self.config_writer.save(path, new_config.clone());
         drop(config);
-        self.notify_overlay();
-        Ok(result)
+        drop(profile);
+        self.sync_pomodoro(&old_config.pomodoro, &new_config.pomodoro);
+        self.notify_overlay(transition_ms);
+        Ok(ConfigUpdate {
+            config: new_config,
+            clamped,
+        })
+    }
+
+    /// Update config, rejecting out-of-range values, and queue it to be
+    /// saved to the active profile
+    pub fn update_config<F>(&self, f: F) -> Result<RainConfig, String>
+    where
+        F: FnOnce(&mut RainConfig),
+    {
+        self.change_config(f, ValidationMode::Strict, None)
+            .map(|update| update.config)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Like `update_config`, but the overlay animates the change over
+    /// `transition_ms` instead of the configured transition duration
+    pub fn update_config_with_transition<F>(
+        &self,
+        f: F,
+        transition_ms: u32,
+    ) -> Result<RainConfig, String>
+    where
+        F: FnOnce(&mut RainConfig),
+    {
+        if f64::from(transition_ms) > schema::TRANSITION_DURATION.max {
+            return Err(format!(
+                "Transition time must be at most {} ms, got {}",
+                schema::TRANSITION_DURATION.max,
+                transition_ms
+            ));
+        }
+        self.change_config(f, ValidationMode::Strict, Some(transition_ms))
+            .map(|update| update.config)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Switch to a preset, animated over `transition_ms` if given
+    pub fn apply_preset(
+        &self,
+        name: &str,
+        transition_ms: Option<u32>,
+    ) -> Result<RainConfig, String> {
+        let preset = presets::find(name).map_err(|e| e.to_string())?;
+        let apply = |c: &mut RainConfig| {
+            preset.config.apply_to(c);
+            c.preset = Some(preset.name.clone());
+        };
+        match transition_ms {
+            None => self.update_config(apply),
+            Some(ms) => self.update_config_with_transition(apply, ms),
+        }
     }
 
     /// Replace entire config
-    pub fn set_config(&self, mut new_config: RainConfig) -> Result<RainConfig, String> {
-        new_config.clamp();
-        new_config.save().map_err(|e| e.to_string())?;
+    pub fn set_config(
+        &self,
+        mut new_config: RainConfig,
+        mode: ValidationMode,
+    ) -> Result<ConfigUpdate, ConfigUpdateError> {
+        // The schema version describes the file, it isn't a setting
+        new_config.version = CONFIG_VERSION;
+        self.change_config(|config| *config = new_config, mode, None)
+    }
+
+    /// Adopt a config read from the active profile's file at `path` (edited
+    /// on disk, or just switched to). It's already saved, so it only goes to
+    /// the timer and the overlay, and a change still waiting to be written
+    /// is dropped rather than saved over it. Returns false if nothing changed.
+    pub fn adopt_config(&self, path: PathBuf, new_config: RainConfig) -> bool {
         let mut config = self.config.lock().unwrap();
-        *config = new_config;
-        let result = config.clone();
+        // Under the config lock, so it comes after every snapshot of the
+        // config being replaced
+        self.config_writer.discard(path);
+        if *config == new_config {
+            return false;
+        }
+        let old_config = std::mem::replace(&mut *config, new_config.clone());
         drop(config);
-        self.notify_overlay();
-        Ok(result)
+        self.sync_pomodoro(&old_config.pomodoro, &new_config.pomodoro);
+        self.notify_overlay(None);
+        true
+    }
+
+    /// List profiles and the active one
+    pub fn profiles(&self) -> Result<ProfileList, String> {
+        Ok(ProfileList {
+            active: self.active_profile.lock().unwrap().clone(),
+            profiles: profiles::list().map_err(|e| e.to_string())?,
+        })
+    }
+
+    /// Create a profile with default settings when `source` is `None`,
+    /// otherwise as a copy of that profile
+    pub fn create_profile(&self, name: &str, source: Option<&str>) -> Result<ProfileList, String> {
+        let active = self.active_profile.lock().unwrap();
+        let config = match source {
+            None => RainConfig::default(),
+            // Unsaved changes to the active profile are only in memory
+            Some(source) if source == *active => self.get_config(),
+            Some(source) => {
+                profiles::ensure_exists(source).map_err(|e| e.to_string())?;
+                let path = profiles::profile_path(source).map_err(|e| e.to_string())?;
+                RainConfig::load(&path).map_err(|e| e.to_string())?
+            }
+        };
+        profiles::create(name, &config).map_err(|e| e.to_string())?;
+        drop(active);
+        self.profiles()
+    }
+
+    /// Load a profile and make it the active one
+    pub fn switch_profile(&self, name: &str) -> Result<RainConfig, String> {
+        let mut active = self.active_profile.lock().unwrap();
+        self.switch_profile_locked(&mut active, name)
+    }
+
+    /// `switch_profile` with the active profile lock already held
+    fn switch_profile_locked(&self, active: &mut String, name: &str) -> Result<RainConfig, String> {
+        profiles::ensure_exists(name).map_err(|e| e.to_string())?;
+        // Pending saves belong to the profile being left
+        self.config_writer.flush();
+        let path = profiles::profile_path(name).map_err(|e| e.to_string())?;
+        let (config, issue) = RainConfig::load_or_recover(&path);
+        *self.config_issue.lock().unwrap() = issue;
+        self.adopt_config(path, config.clone());
+        *active = name.to_string();
+        profiles::store_active(name).map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    /// Rename a profile, following it if it's the active one
+    pub fn rename_profile(&self, old: &str, new: &str) -> Result<ProfileList, String> {
+        let mut active = self.active_profile.lock().unwrap();
+        let renaming_active = old == *active;
+        if renaming_active {
+            self.config_writer.flush();
+        }
+        profiles::rename(old, new).map_err(|e| e.to_string())?;
+        if renaming_active {
+            *active = new.to_string();
+            profiles::store_active(new).map_err(|e| e.to_string())?;
+        }
+        drop(active);
+        self.profiles()
+    }
+
+    /// Delete a profile. Deleting the active profile switches to the default one.
+    pub fn delete_profile(&self, name: &str) -> Result<ProfileList, String> {
+        profiles::ensure_deletable(name).map_err(|e| e.to_string())?;
+        // Held throughout, so the profile can't be switched to meanwhile
+        let mut active = self.active_profile.lock().unwrap();
+        if name == *active {
+            self.switch_profile_locked(&mut active, DEFAULT_PROFILE)?;
+        }
+        profiles::delete(name).map_err(|e| e.to_string())?;
+        drop(active);
+        self.profiles()
+    }
+
+    /// Rename a user preset along with the playlist entries that play it,
+    /// returning its new name
+    pub fn rename_preset(&self, old: &str, new: &str) -> Result<String, String> {
+        // Held throughout, so the player can't reach an entry in between
+        let mut playlist = self.playlist.lock().unwrap();
+        let new = presets::rename(old, new).map_err(|e| e.to_string())?;
+        playlist
+            .rename_preset(old, &new)
+            .map_err(|e| format!("Renamed, but the playlist was not updated: {}", e))?;
+        Ok(new)
+    }
+
+    /// Delete a user preset, unless the playlist still plays it
+    pub fn delete_preset(&self, name: &str) -> Result<(), String> {
+        let playlist = self.playlist.lock().unwrap();
+        playlist.ensure_unused(name).map_err(|e| e.to_string())?;
+        presets::delete(name).map_err(|e| e.to_string())
+    }
+
+    /// Playlist and playback position
+    pub fn playlist_status(&self) -> PlaylistStatus {
+        self.playlist.lock().unwrap().status()
+    }
+
+    /// Move the playlist, apply the preset it lands on, and let the
+    /// playlist thread pick up the new deadline
+    fn change_playlist<F>(&self, f: F) -> Result<PlaylistStatus, String>
+    where
+        F: FnOnce(&mut PlaylistState) -> Result<Option<PlaylistEntry>, PlaylistError>,
+    {
+        let mut playlist = self.playlist.lock().unwrap();
+        let entry = f(&mut playlist).map_err(|e| e.to_string())?;
+        let transition_ms = playlist.transition_ms();
+        let status = playlist.status();
+        drop(playlist);
+        if let Some(tx) = self.playlist_tx.lock().unwrap().as_ref() {
+            let _ = tx.send(());
+        }
+        if let Some(entry) = entry {
+            self.apply_preset(&entry.preset, transition_ms)?;
+        }
+        Ok(status)
+    }
+
+    /// Replace the playlist; a running playlist restarts from the top
+    pub fn set_playlist(&self, playlist: Playlist) -> Result<PlaylistStatus, String> {
+        self.change_playlist(|p| p.set(playlist))
+    }
+
+    /// Start or resume the playlist, applying its current preset
+    pub fn start_playlist(&self) -> Result<PlaylistStatus, String> {
+        self.change_playlist(|p| p.start().map(Some))
+    }
+
+    pub fn stop_playlist(&self) -> Result<PlaylistStatus, String> {
+        self.change_playlist(|p| {
+            p.stop();
+            Ok(None)
+        })
+    }
+
+    /// Apply the next preset in the playlist, or the previous one when
+    /// `forward` is false
+    pub fn skip_playlist(&self, forward: bool) -> Result<PlaylistStatus, String> {
+        self.change_playlist(|p| p.skip(forward).map(Some))
     }
 }
 
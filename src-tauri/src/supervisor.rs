@@ -0,0 +1,178 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::rain;
+use crate::rain::stats::OverlayStats;
+use crate::state::{AppState, OverlayHealth, OverlaySignal};
+
+/// Event emitted to the UI whenever the overlay health changes
+pub const HEALTH_EVENT: &str = "overlay-health";
+
+/// Delay before the first restart, doubled after each failure
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A run lasting this long counts as healthy and resets the backoff
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// Consecutive failed runs before the supervisor gives up
+const MAX_ATTEMPTS: u32 = 8;
+
+/// How long app exit waits for the overlay to release its resources
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Supervisor thread handle, managed as Tauri state
+struct Supervisor {
+    thread: Mutex<Option<thread::JoinHandle<()>>>,
+    /// Stops the supervisor, waking it from a backoff sleep
+    stop_tx: mpsc::Sender<()>,
+}
+
+/// Spawn the overlay supervisor on a dedicated OS thread. The overlay runs a
+/// blocking Wayland event loop; the supervisor restarts it with backoff when
+/// it fails or panics (compositor restart, EGL failure, ...).
+pub fn spawn(app: &AppHandle) {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let handle = app.clone();
+    let thread = thread::Builder::new()
+        .name("raindesk-overlay".to_string())
+        .spawn(move || supervise(&handle, &stop_rx))
+        .expect("failed to spawn overlay thread");
+    app.manage(Supervisor {
+        thread: Mutex::new(Some(thread)),
+        stop_tx,
+    });
+}
+
+/// Stop the overlay and wait, up to `SHUTDOWN_TIMEOUT`, for it to release its
+/// Wayland and EGL resources. Only the first call does anything.
+pub fn shutdown(app: &AppHandle) {
+    let supervisor = app.state::<Supervisor>();
+    let Some(thread) = supervisor.thread.lock().unwrap().take() else {
+        return;
+    };
+    eprintln!("[raindesk overlay] Shutting down...");
+    // Stop first: a run starting right now sees it once its sender is stored
+    let _ = supervisor.stop_tx.send(());
+    if let Some(tx) = app.state::<AppState>().overlay_tx.lock().unwrap().as_ref() {
+        let _ = tx.send(OverlaySignal::Shutdown);
+    }
+
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while !thread.is_finished() {
+        if Instant::now() >= deadline {
+            eprintln!(
+                "[raindesk overlay] Overlay did not stop within {:?}, exiting anyway",
+                SHUTDOWN_TIMEOUT
+            );
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let _ = thread.join();
+    eprintln!("[raindesk overlay] Overlay stopped");
+}
+
+fn supervise(app: &AppHandle, stop_rx: &mpsc::Receiver<()>) {
+    let state = app.state::<AppState>();
+    let mut attempt = 0;
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        // Fresh channel per run; the previous receiver went away with the old overlay
+        let (overlay_tx, overlay_rx) = calloop::channel::channel();
+        *state.overlay_tx.lock().unwrap() = Some(overlay_tx);
+        if !matches!(stop_rx.try_recv(), Err(TryRecvError::Empty)) {
+            publish(app, OverlayHealth::Stopped);
+            return;
+        }
+        let (stats_tx, stats_rx) = mpsc::channel();
+        spawn_stats_relay(app.clone(), stats_rx);
+        publish(app, OverlayHealth::Starting);
+
+        let started = Instant::now();
+        let config = state.config.clone();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            rain::overlay::run_overlay(config, overlay_rx, stats_tx, || {
+                publish(app, OverlayHealth::Running)
+            })
+        }));
+
+        let reason = match result {
+            // Clean exit: shutdown was requested
+            Ok(Ok(())) => {
+                publish(app, OverlayHealth::Stopped);
+                return;
+            }
+            Ok(Err(e)) => e,
+            Err(e) => format!("panic: {}", panic_message(e.as_ref())),
+        };
+        eprintln!("[raindesk overlay] Error: {}", reason);
+
+        if started.elapsed() >= STABLE_RUN {
+            attempt = 0;
+            backoff = INITIAL_BACKOFF;
+        }
+        attempt += 1;
+        if attempt > MAX_ATTEMPTS {
+            eprintln!(
+                "[raindesk overlay] Giving up after {} attempts",
+                MAX_ATTEMPTS
+            );
+            *state.overlay_tx.lock().unwrap() = None;
+            publish(app, OverlayHealth::Failed { reason });
+            return;
+        }
+
+        publish(
+            app,
+            OverlayHealth::Restarting {
+                attempt,
+                reason,
+                retry_in_ms: backoff.as_millis() as u64,
+            },
+        );
+        if !matches!(stop_rx.recv_timeout(backoff), Err(RecvTimeoutError::Timeout)) {
+            publish(app, OverlayHealth::Stopped);
+            return;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Store stats snapshots from one overlay run in `AppState`. The relay ends,
+/// clearing the stats, when the overlay drops its sender.
+fn spawn_stats_relay(app: AppHandle, stats_rx: mpsc::Receiver<OverlayStats>) {
+    let spawned = thread::Builder::new()
+        .name("raindesk-overlay-stats".to_string())
+        .spawn(move || {
+            for stats in stats_rx {
+                *app.state::<AppState>().overlay_stats.lock().unwrap() = stats;
+            }
+            *app.state::<AppState>().overlay_stats.lock().unwrap() = OverlayStats::default();
+        });
+    if let Err(e) = spawned {
+        eprintln!("[raindesk overlay] Stats relay not started: {}", e);
+    }
+}
+
+/// Record the health in `AppState` and notify the UI
+fn publish(app: &AppHandle, health: OverlayHealth) {
+    *app.state::<AppState>().overlay_health.lock().unwrap() = health.clone();
+    let _ = app.emit(HEALTH_EVENT, health);
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
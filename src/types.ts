@@ -7,7 +7,30 @@ export interface RainColor {
   a: number;
 }
 
+export type OverlayLayer = "background" | "bottom" | "top" | "overlay";
+
+export type Easing = "linear" | "ease-in" | "ease-out" | "ease-in-out";
+
+export interface TransitionSettings {
+  duration_ms: number;
+  easing: Easing;
+}
+
+export interface RainRegion {
+  anchor_top: boolean;
+  anchor_bottom: boolean;
+  anchor_left: boolean;
+  anchor_right: boolean;
+  margin_top: number;
+  margin_right: number;
+  margin_bottom: number;
+  margin_left: number;
+  width: number | null;
+  height: number | null;
+}
+
 export interface RainConfig {
+  version: number;
   enabled: boolean;
   intensity: number;
   speed: number;
@@ -19,6 +42,66 @@ export interface RainConfig {
   splash_enabled: boolean;
   splash_intensity: number;
   preset: string | null;
+  layer: OverlayLayer;
+  region: RainRegion;
+  max_fps: number;
+  close_to_background: boolean;
+  transition: TransitionSettings;
+  pomodoro: PomodoroSettings;
+}
+
+export type FieldSection =
+  | "rain"
+  | "splash"
+  | "overlay"
+  | "transition"
+  | "region"
+  | "pomodoro";
+
+// Allowed range of a numeric config field, from get_config_schema
+export interface FieldSpec {
+  field: string;
+  label: string;
+  section: FieldSection;
+  min: number;
+  max: number;
+  step: number;
+  unit: string;
+  off_value: number | null;
+  optional: boolean;
+}
+
+export interface FieldError {
+  field: string;
+  message: string;
+  min: number | null;
+  max: number | null;
+  value: unknown;
+  clamped_to: unknown;
+}
+
+export interface ValidationError {
+  errors: FieldError[];
+}
+
+/** Why set_config or set_config_lenient didn't apply an update */
+export type ConfigUpdateError =
+  | ({ kind: "invalid" } & ValidationError)
+  | { kind: "profile"; message: string };
+
+export interface ConfigUpdate {
+  config: RainConfig;
+  clamped: FieldError[];
+}
+
+export interface ConfigIssue {
+  message: string;
+  backup_path: string | null;
+}
+
+export interface ProfileList {
+  active: string;
+  profiles: string[];
 }
 
 export interface PresetConfig {
@@ -37,29 +120,173 @@ export interface Preset {
   name: string;
   description: string;
   config: PresetConfig;
+  builtin: boolean;
+  favorite: boolean;
+}
+
+export interface PlaylistEntry {
+  preset: string;
+  dwell_secs: number;
+}
+
+export interface Playlist {
+  shuffle: boolean;
+  transition_ms: number | null;
+  entries: PlaylistEntry[];
+}
+
+export interface PlaylistStatus {
+  playlist: Playlist;
+  running: boolean;
+  // Index into playlist.entries of the current preset
+  current: number | null;
+  ends_at_ms: number | null;
+  // Presets passed over on the last move because they no longer exist
+  skipped: string[];
+}
+
+export interface ImportedPreset {
+  name: string;
+  renamed_from: string | null;
+  clamped: FieldError[];
+}
+
+export type OverlayHealth =
+  | { status: "starting" }
+  | { status: "running" }
+  | { status: "restarting"; attempt: number; reason: string; retry_in_ms: number }
+  | { status: "failed"; reason: string }
+  | { status: "stopped" };
+
+export interface OutputStats {
+  name: string;
+  width: number;
+  height: number;
+  scale: number;
+  active: boolean;
+  fps: number;
+  frame_time_p50_ms: number;
+  frame_time_p95_ms: number;
+  frame_time_p99_ms: number;
+  frame_time_max_ms: number;
+  drops: number;
+  splashes: number;
+}
+
+export interface OverlayStats {
+  gl_renderer: string | null;
+  outputs: OutputStats[];
 }
 
 export type PomodoroPhase = "Work" | "ShortBreak" | "LongBreak";
 export type PomodoroStatus = "Idle" | "Running" | "Paused";
+export type ResumePolicy = "catch-up" | "pause" | "complete-phase";
+
+export interface PomodoroSettings {
+  work_duration_secs: number;
+  short_break_duration_secs: number;
+  long_break_duration_secs: number;
+  sessions_until_long_break: number;
+  resume_policy: ResumePolicy;
+  resume_on_launch: boolean;
+  daily_goal_mins: number;
+}
 
 export interface PomodoroState {
   phase: PomodoroPhase;
   status: PomodoroStatus;
   remaining_secs: number;
+  deadline_ms: number | null;
   completed_sessions: number;
   work_duration_secs: number;
   short_break_duration_secs: number;
   long_break_duration_secs: number;
   sessions_until_long_break: number;
+  resume_policy: ResumePolicy;
+}
+
+export type PomodoroEventKind =
+  | "start"
+  | "resume"
+  | "complete"
+  | "skip"
+  | "pause"
+  | "reset";
+
+// An entry of the pomodoro history, from get_pomodoro_history
+export interface PomodoroEvent {
+  at_ms: number;
+  kind: PomodoroEventKind;
+  phase: PomodoroPhase;
+  phase_secs: number;
+  ran_secs: number;
+}
+
+export interface DayStats {
+  date: string;
+  focus_secs: number;
+  completed_sessions: number;
+  skipped_sessions: number;
+  goal_met: boolean;
+}
+
+export interface WeekStats {
+  week_start: string;
+  focus_secs: number;
+  completed_sessions: number;
+  skipped_sessions: number;
+}
+
+// Focus statistics from get_pomodoro_stats
+export interface FocusStats {
+  days: DayStats[];
+  weeks: WeekStats[];
+  focus_secs: number;
+  completed_sessions: number;
+  skipped_sessions: number;
+  average_pause_secs: number | null;
+  daily_goal_mins: number;
+  longest_streak_days: number;
+  // [weekday, Monday first][hour of the day]
+  hourly_focus_secs: number[][];
 }
 
 // Helper functions
+export function getField(config: RainConfig, path: string): unknown {
+  return path
+    .split(".")
+    .reduce<unknown>((value, key) => (value as Record<string, unknown>)?.[key], config);
+}
+
+export function withField(config: RainConfig, path: string, value: unknown): RainConfig {
+  const copy = JSON.parse(JSON.stringify(config));
+  const keys = path.split(".");
+  const last = keys.pop()!;
+  keys.reduce((obj, key) => obj[key], copy)[last] = value;
+  return copy;
+}
+
+export function formatFieldValue(spec: FieldSpec, value: number): string {
+  if (spec.unit === "%") {
+    return `${(value * 100).toFixed(0)}%`;
+  }
+  const decimals = spec.step >= 1 ? 0 : Math.ceil(-Math.log10(spec.step));
+  return `${value.toFixed(decimals)}${spec.unit}`;
+}
+
 export function formatTime(totalSecs: number): string {
   const minutes = Math.floor(totalSecs / 60);
   const seconds = totalSecs % 60;
   return `${minutes.toString().padStart(2, "0")}:${seconds.toString().padStart(2, "0")}`;
 }
 
+export function formatDuration(totalSecs: number): string {
+  const totalMins = Math.round(totalSecs / 60);
+  const hours = Math.floor(totalMins / 60);
+  const minutes = totalMins % 60;
+  return hours > 0 ? `${hours}h ${minutes}m` : `${minutes}m`;
+}
+
 export function colorToHex(color: RainColor): string {
   const r = color.r.toString(16).padStart(2, "0");
   const g = color.g.toString(16).padStart(2, "0");